@@ -0,0 +1,122 @@
+use crate::middleware::auth::ApiKeyIdentity;
+use crate::model::config::ApiKeyScope;
+use crate::model::conversation::{Conversation, ConversationSummary, SESSION_ID_KEY};
+use crate::store::conversation_store::ConversationStore;
+use actix_session::Session;
+use actix_web::{delete, get, web, HttpMessage, HttpRequest, HttpResponse, Result};
+use log::debug;
+
+/// Lists the caller's chat conversations, most recently active first. Admin keys see every conversation.
+#[utoipa::path(
+    tag = "chat",
+    responses((status = 200, description = "Conversation summaries", body = Vec<ConversationSummary>)),
+    security((), ("api_key" = []))
+)]
+#[get("/api/conversations")]
+async fn list_conversations(
+    req: HttpRequest,
+    session: Session,
+    conversation_store: web::Data<dyn ConversationStore>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let conversations: Vec<Conversation> = if is_admin(&req) {
+        conversation_store.list()?
+    } else {
+        match session.get::<String>(SESSION_ID_KEY)? {
+            Some(session_id) => conversation_store.get(&session_id)?.into_iter().collect(),
+            None => Vec::new(),
+        }
+    };
+
+    let mut summaries: Vec<ConversationSummary> = conversations
+        .iter()
+        .map(|conversation| conversation.summary())
+        .collect();
+
+    // Most recently active first
+    summaries.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+    debug!("Found {} conversations", summaries.len());
+
+    Ok(HttpResponse::Ok().json(summaries))
+}
+
+/// Returns the full message history of the caller's conversation. Admin keys can read any conversation.
+#[utoipa::path(
+    tag = "chat",
+    params(("session_id" = String, Path, description = "Conversation ID from the X-Session-Id header")),
+    responses(
+        (status = 200, description = "The conversation", body = Conversation),
+        (status = 404, description = "No conversation with that ID in this session", body = String, content_type = "text/plain")
+    ),
+    security((), ("api_key" = []))
+)]
+#[get("/api/conversations/{session_id}")]
+async fn get_conversation(
+    session_id: web::Path<String>,
+    req: HttpRequest,
+    session: Session,
+    conversation_store: web::Data<dyn ConversationStore>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    debug!("Session ID: {}", session_id);
+
+    if !can_access(&req, &session, &session_id)? {
+        return Ok(HttpResponse::NotFound().body("Conversation not found."));
+    }
+
+    match conversation_store.get(&session_id)? {
+        Some(conversation) => Ok(HttpResponse::Ok().json(conversation)),
+        None => Ok(HttpResponse::NotFound().body("Conversation not found.")),
+    }
+}
+
+/// Deletes the caller's conversation. Admin keys can delete any conversation.
+#[utoipa::path(
+    tag = "chat",
+    params(("session_id" = String, Path, description = "Conversation ID from the X-Session-Id header")),
+    responses(
+        (status = 204, description = "The conversation was deleted"),
+        (status = 404, description = "No conversation with that ID in this session", body = String, content_type = "text/plain")
+    ),
+    security((), ("api_key" = []))
+)]
+#[delete("/api/conversations/{session_id}")]
+async fn delete_conversation(
+    session_id: web::Path<String>,
+    req: HttpRequest,
+    session: Session,
+    conversation_store: web::Data<dyn ConversationStore>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    debug!("Session ID: {}", session_id);
+
+    if !can_access(&req, &session, &session_id)? {
+        return Ok(HttpResponse::NotFound().body("Conversation not found."));
+    }
+
+    if conversation_store.delete(&session_id)? {
+        // The next chat message starts a new conversation
+        if session.get::<String>(SESSION_ID_KEY)?.as_deref() == Some(session_id.as_str()) {
+            session.remove(SESSION_ID_KEY);
+        }
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Ok(HttpResponse::NotFound().body("Conversation not found."))
+    }
+}
+
+fn is_admin(req: &HttpRequest) -> bool {
+    req.extensions()
+        .get::<ApiKeyIdentity>()
+        .is_some_and(|x| matches!(x.scope, ApiKeyScope::Admin))
+}
+
+// Callers only reach the conversation stored in their own session, admins reach all of them
+fn can_access(
+    req: &HttpRequest,
+    session: &Session,
+    session_id: &str,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    if is_admin(req) {
+        return Ok(true);
+    }
+
+    Ok(session.get::<String>(SESSION_ID_KEY)?.as_deref() == Some(session_id))
+}
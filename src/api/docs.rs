@@ -0,0 +1,61 @@
+use crate::model::chat_completion_request::{
+    ChatCompletionRequest, JsonSchemaFormat, Message, RequestTool, ResponseFormat, ResponseType,
+    ToolCall, ToolCallFunction, ToolFunction,
+};
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Month of AI API", description = "Movie search, recommendations and chat backed by Azure OpenAI."),
+    paths(
+        super::movies::list_movies,
+        super::movies::get_movie,
+        super::movies::ask_question,
+        super::movies::get_movie_criteria,
+        super::movies::similar_movies,
+        super::movies::recommend_movies,
+        super::movies::actor_movies,
+        super::movies::movie_chat,
+        super::conversations::list_conversations,
+        super::conversations::get_conversation,
+        super::conversations::delete_conversation,
+        super::scraper::embed_movie_json,
+        super::scraper::import_movie_db,
+        super::scraper::index_movies,
+        super::health::healthz,
+        super::health::readyz,
+    ),
+    components(schemas(
+        ChatCompletionRequest,
+        Message,
+        ToolCall,
+        ToolCallFunction,
+        ResponseFormat,
+        JsonSchemaFormat,
+        ResponseType,
+        RequestTool,
+        ToolFunction,
+    )),
+    modifiers(&ApiKeySecurity),
+    tags(
+        (name = "movies", description = "Movie lookup, criteria extraction and similarity"),
+        (name = "chat", description = "Recommendation chat and stored conversations"),
+        (name = "admin", description = "Data ingestion, admin API key required"),
+        (name = "health", description = "Liveness and readiness")
+    )
+)]
+pub struct ApiDoc;
+
+struct ApiKeySecurity;
+
+impl Modify for ApiKeySecurity {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "api_key",
+                SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+            );
+        }
+    }
+}
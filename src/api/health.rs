@@ -0,0 +1,151 @@
+use crate::model::cache::Cache;
+use crate::model::config::Config;
+use crate::model::health::{DataCoverage, DependencyStatus, HealthReport};
+use crate::util::movie_helper::{load_movie_embeddings, load_top_movies};
+use actix_web::{get, web, HttpResponse, Result};
+use log::{debug, warn};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_REPORTED_MISSING_IDS: usize = 20;
+
+/// Reports that the server is up. Data and dependencies are checked by /readyz.
+#[utoipa::path(
+    tag = "health",
+    responses((status = 200, description = "The server is up", body = HealthReport))
+)]
+#[get("/healthz")]
+async fn healthz() -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(HealthReport {
+        status: String::from("ok"),
+        data: None,
+        dependencies: None,
+    }))
+}
+
+/// Reports data coverage and whether Azure OpenAI and Azure Search answer.
+#[utoipa::path(
+    tag = "health",
+    responses(
+        (status = 200, description = "Ready to serve requests", body = HealthReport),
+        (status = 503, description = "Data is missing or a dependency is unreachable", body = HealthReport)
+    )
+)]
+#[get("/readyz")]
+async fn readyz(config: web::Data<Config>, cache: web::Data<Mutex<Cache>>) -> Result<HttpResponse> {
+    let data = data_coverage(&cache);
+
+    let client = reqwest::Client::builder()
+        .timeout(PROBE_TIMEOUT)
+        .build()
+        .unwrap_or_default();
+
+    // Both probes only list metadata, so they don't spend any tokens
+    let open_ai_probe = probe(
+        &client,
+        "azure_open_ai",
+        format!(
+            "{}openai/models?api-version={}",
+            config.open_ai.url, config.open_ai.api_version
+        ),
+        &config.open_ai.key,
+    );
+    let search_probe = probe(
+        &client,
+        "azure_search",
+        format!(
+            "{}indexes/idx-movies/docs/$count?api-version={}",
+            config.azure_search.url, config.azure_search.api_version
+        ),
+        &config.azure_search.key,
+    );
+    let (open_ai_status, search_status) = futures::join!(open_ai_probe, search_probe);
+    let dependencies = vec![open_ai_status, search_status];
+
+    let ready =
+        data.catalogue_loaded && data.embeddings_loaded && dependencies.iter().all(|x| x.reachable);
+
+    let report = HealthReport {
+        status: String::from(if ready { "ready" } else { "not_ready" }),
+        data: Some(data),
+        dependencies: Some(dependencies),
+    };
+    debug!("Readiness: {:?}", report);
+
+    if ready {
+        Ok(HttpResponse::Ok().json(report))
+    } else {
+        Ok(HttpResponse::ServiceUnavailable().json(report))
+    }
+}
+
+fn data_coverage(cache: &Mutex<Cache>) -> DataCoverage {
+    // Loads the JSON files into the cache if they haven't been yet
+    let top_movies_found = load_top_movies(cache);
+    let movie_embeddings_found = load_movie_embeddings(cache);
+
+    let cache_lock = cache.lock().unwrap();
+    let top_movies_lock = cache_lock.top_movies.lock().unwrap();
+    let movie_embeddings_lock = cache_lock.movie_embeddings.lock().unwrap();
+
+    let embedded_movie_ids: HashSet<i32> = movie_embeddings_lock
+        .iter()
+        .filter(|x| {
+            x.embeddings
+                .as_ref()
+                .is_some_and(|embeddings| !embeddings.data.is_empty())
+        })
+        .map(|x| x.movie_id)
+        .collect();
+
+    let missing_embedding_ids: Vec<i32> = top_movies_lock
+        .iter()
+        .filter(|x| !embedded_movie_ids.contains(&x.id))
+        .map(|x| x.id)
+        .collect();
+
+    DataCoverage {
+        catalogue_loaded: top_movies_found && !top_movies_lock.is_empty(),
+        embeddings_loaded: movie_embeddings_found && !embedded_movie_ids.is_empty(),
+        movie_count: top_movies_lock.len(),
+        embedding_count: embedded_movie_ids.len(),
+        movies_missing_embeddings: missing_embedding_ids.len(),
+        missing_embedding_ids: missing_embedding_ids
+            .into_iter()
+            .take(MAX_REPORTED_MISSING_IDS)
+            .collect(),
+    }
+}
+
+async fn probe(client: &reqwest::Client, name: &str, url: String, key: &str) -> DependencyStatus {
+    let started = Instant::now();
+    let result = client.get(&url).header("api-key", key).send().await;
+    let latency_ms = started.elapsed().as_millis();
+
+    // Strip the query string so the report doesn't echo configuration details
+    let url = url.split('?').next().unwrap_or_default().to_string();
+
+    match result {
+        Ok(response) => DependencyStatus {
+            name: name.to_string(),
+            url,
+            reachable: response.status().is_success(),
+            status_code: Some(response.status().as_u16()),
+            error: None,
+            latency_ms,
+        },
+        Err(e) => {
+            warn!("{} probe failed: {}", name, e);
+            DependencyStatus {
+                name: name.to_string(),
+                url,
+                reachable: false,
+                status_code: None,
+                error: Some(e.to_string()),
+                latency_ms,
+            }
+        }
+    }
+}
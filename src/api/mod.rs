@@ -1,2 +1,5 @@
+pub mod conversations;
+pub mod docs;
+pub mod health;
 pub mod movies;
 pub mod scraper;
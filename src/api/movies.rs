@@ -1,65 +1,240 @@
+use crate::model::answered_question::AnsweredQuestion;
 use crate::model::cache::Cache;
 use crate::model::chat_completion_request::{
-    ChatCompletionRequest, Message, RequestTool, ResponseFormat, ResponseType::JsonObject,
-    ResponseType::Text, ToolFunction,
+    ChatCompletionRequest, Message, RequestTool, ResponseFormat, ResponseType::Text, ToolChoice,
+    ToolChoiceMode, ToolFunction,
+};
+use crate::model::chat_completion_response::{
+    ChatCompletionChoice, ChatCompletionResponse, ToolCall,
 };
-use crate::model::chat_completion_response::{ChatCompletionChoice, ChatCompletionResponse};
 use crate::model::config::Config;
+use crate::model::conversation::{
+    Conversation, MovieChatRequest, SESSION_ID_HEADER, SESSION_ID_KEY,
+};
 use crate::model::movies::movie::TopRatedMovie;
-use crate::model::movies::{movie::Movie, movie_criteria::MovieCriteria};
-use crate::model::query::{InputObject, QuestionObject};
-use crate::util::movie_helper::{can_load_data, filter_movies, find_similar_movies};
-use crate::util::response_helper::extract_message;
-use crate::util::tool_helper::return_filter_tool;
+use crate::model::movies::movie_criteria::SortDirection;
+use crate::model::movies::movie_criteria::{CriteriaError, MovieCriteria};
+use crate::model::movies::movie_page::MoviePage;
+use crate::model::movies::recommendation_request::RecommendationRequest;
+use crate::model::movies::similar_movie::SimilarMovie;
+use crate::model::query::{InputObject, MovieListQuery, QuestionObject, SimilarMoviesQuery};
+use crate::store::conversation_store::ConversationStore;
+use crate::store::movie_repository::MovieRepository;
+use crate::tools::{tool_error, tool_message, ToolContext, ToolRegistry};
+use crate::util::answer_cache_helper::{find_exact_answer, find_similar_answer, remember_answer};
+use crate::util::chat_completion_helper::send_chat_completion;
+use crate::util::context_helper::{estimate_text_tokens, fit_to_context_window};
+use crate::util::criteria_helper::{parse_criteria, validate_criteria};
+use crate::util::date_helper::{format_date, today};
+use crate::util::embedding_helper::create_embedding;
+use crate::util::movie_helper::{
+    find_similar_movies, load_movie_embeddings, movie_embedding, preference_vector,
+    rank_by_embedding, sort_movies,
+};
+use crate::util::rerank_helper::{rerank, RankedMovie, RerankOptions};
+use crate::util::response_helper::{collect_tool_calls, extract_message, parse_filter_arguments};
+use crate::util::tool_helper::{
+    describe_criteria_fields, return_filter_tool, strict_criteria_schema, FILTER_TOOL_NAME,
+};
+use actix_session::Session;
 use actix_web::http::header::ContentType;
 use actix_web::{get, post, web, HttpResponse, Result};
 use log::{debug, error, info, warn};
 use serde_json::{from_str, to_string};
 use spinners::{Spinner, Spinners};
+use std::collections::HashSet;
 use std::sync::Mutex;
+use uuid::Uuid;
+
+const DEFAULT_PAGE_SIZE: usize = 20;
+const MAX_PAGE_SIZE: usize = 100;
+// The first answer plus one retry with the validation errors
+const MAX_CRITERIA_ATTEMPTS: usize = 2;
+// Similar movies returned when the request doesn't set k, and the most it may ask for
+const DEFAULT_SIMILAR_MOVIES: usize = 10;
+const MAX_SIMILAR_MOVIES: usize = 100;
+// Candidates considered when re-ranking similar movies for diversity
+const RERANK_POOL_SIZE: usize = 50;
+// Recommendations returned when the request doesn't set a limit, and the most it may ask for
+const DEFAULT_RECOMMENDATIONS: usize = 10;
+const MAX_RECOMMENDATIONS: usize = 50;
+const DEFAULT_DISLIKED_WEIGHT: f32 = 0.5;
+// Rounds of lookup tool calls movie_chat answers before the model has to reply without tools
+const MAX_TOOL_ROUNDS: usize = 4;
+
+/// Lists movies with optional filters, sorting and paging.
+#[utoipa::path(
+    tag = "movies",
+    params(MovieListQuery),
+    responses(
+        (status = 200, description = "One page of matching movies", body = MoviePage),
+        (status = 400, description = "Invalid filters or an unknown sort field", body = String, content_type = "text/plain"),
+        (status = 500, description = "The movie source couldn't be read", body = String, content_type = "text/plain")
+    ),
+    security((), ("api_key" = []))
+)]
+#[get("/api/movies")]
+async fn list_movies(
+    query: web::Query<MovieListQuery>,
+    movie_repository: web::Data<dyn MovieRepository>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    debug!("Movie list query: {:?}", query);
+
+    let mut movies = if query.has_filters() {
+        let criteria = match validate_criteria(query.criteria()) {
+            Ok(criteria) => criteria,
+            Err(errors) => {
+                let messages: Vec<String> = errors.iter().map(|x| x.to_string()).collect();
+                return Ok(bad_request(&messages.join("; ")));
+            }
+        };
+
+        movie_repository.find_movies(criteria).await?
+    } else {
+        movie_repository.list_movies().await?
+    };
 
-async fn fetch_movie_details(
-    movie_id: &str,
-    config: web::Data<Config>,
-) -> Result<Movie, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
+    if let Some(title) = &query.title {
+        let title = title.to_lowercase();
+        movies.retain(|x| x.title.to_lowercase().contains(&title));
+    }
 
-    // Fetch movie details for movie_id
-    let movie_details_response = client
-        .get(format!(
-            "{}indexes/idx-movies/docs/{}?api-version={}",
-            config.azure_search.url, movie_id, config.azure_search.api_version
-        ))
-        .header("Content-Type", "application/json")
-        .header("api-key", config.azure_search.key.clone())
-        .send()
-        .await?;
+    if let Some(sort) = &query.sort {
+        let direction = query.direction.clone().unwrap_or(SortDirection::Asc);
+        if let Err(e) = sort_movies(&mut movies, sort, &direction) {
+            return Ok(HttpResponse::BadRequest()
+                .insert_header(ContentType(mime::TEXT_PLAIN))
+                .body(e));
+        }
+    }
 
-    let movie_details = movie_details_response.text().await?;
-    let movie: Movie = from_str(&movie_details)?;
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query
+        .page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let total = movies.len();
+
+    let items: Vec<TopRatedMovie> = movies
+        .into_iter()
+        .skip((page - 1).saturating_mul(page_size))
+        .take(page_size)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(MoviePage {
+        items,
+        page,
+        page_size,
+        total,
+    }))
+}
 
-    Ok(movie)
+/// Returns a single movie.
+#[utoipa::path(
+    tag = "movies",
+    params(("movie_id" = i32, Path, description = "TMDB ID of the movie")),
+    responses(
+        (status = 200, description = "The movie", body = TopRatedMovie),
+        (status = 404, description = "No movie with that ID", body = String, content_type = "text/plain")
+    ),
+    security((), ("api_key" = []))
+)]
+#[get("/api/movies/{movie_id}")]
+async fn get_movie(
+    movie_id: web::Path<i32>,
+    movie_repository: web::Data<dyn MovieRepository>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    debug!("Movie ID: {}", movie_id);
+
+    match movie_repository.get_movie(movie_id.into_inner()).await? {
+        Some(movie) => Ok(HttpResponse::Ok().json(movie)),
+        None => Ok(HttpResponse::NotFound()
+            .insert_header(ContentType(mime::TEXT_PLAIN))
+            .body("Movie not found.")),
+    }
 }
 
+/// Answers a free-form question about a single movie.
+#[utoipa::path(
+    tag = "movies",
+    params(
+        ("movie_id" = i32, Path, description = "TMDB ID of the movie"),
+        QuestionObject
+    ),
+    responses(
+        (status = 200, description = "The answer", body = String, content_type = "text/plain"),
+        (status = 404, description = "No movie with that ID", body = String, content_type = "text/plain"),
+        (status = 500, description = "The movie or the model couldn't be reached", body = String, content_type = "text/plain")
+    ),
+    security((), ("api_key" = []))
+)]
 #[get("/api/movies/{movie_id}/askQuestion")]
 async fn ask_question(
-    movie_id: web::Path<String>,              // Extract movieID from path
+    movie_id: web::Path<i32>,                 // Extract movieID from path
     query_object: web::Query<QuestionObject>, // Extract question from query string
     config: web::Data<Config>,
+    cache: web::Data<Mutex<Cache>>,
+    movie_repository: web::Data<dyn MovieRepository>,
 ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
     debug!("Movie ID: {}", movie_id);
     debug!("Question: {}", query_object.question);
     debug!("Parsed config: {:?}", config);
 
     let config_data = config.clone();
+    let movie_id = movie_id.into_inner();
+
+    let movie = match movie_repository.get_movie(movie_id).await? {
+        Some(movie) => movie,
+        None => {
+            return Ok(HttpResponse::NotFound()
+                .insert_header(ContentType(mime::TEXT_PLAIN))
+                .body("Movie not found."))
+        }
+    };
+    debug!("{:?}", movie);
+
+    let mut question_embedding: Option<Vec<f32>> = None;
+
+    if config_data.answer_cache.enabled {
+        let exact_answer = find_exact_answer(
+            movie_id,
+            &query_object.question,
+            &cache.lock().unwrap().answered_questions.lock().unwrap(),
+        );
+
+        if let Some(answer) = exact_answer {
+            debug!("Returning cached answer");
+            return Ok(HttpResponse::Ok()
+                .insert_header(ContentType(mime::TEXT_PLAIN))
+                .body(answer));
+        }
+
+        match create_embedding(&query_object.question, &config_data).await {
+            Ok(embedding) => {
+                let similar_answer = find_similar_answer(
+                    movie_id,
+                    &embedding,
+                    &cache.lock().unwrap().answered_questions.lock().unwrap(),
+                    config_data.answer_cache.similarity_threshold,
+                );
+
+                if let Some(answer) = similar_answer {
+                    debug!("Returning cached answer for a similar question");
+                    return Ok(HttpResponse::Ok()
+                        .insert_header(ContentType(mime::TEXT_PLAIN))
+                        .body(answer));
+                }
+
+                question_embedding = Some(embedding);
+            }
+            Err(e) => warn!("Error embedding question, skipping answer cache: {}", e),
+        }
+    }
 
     let client = reqwest::Client::new();
 
     let mut sp = Spinner::new(Spinners::Dots9, "\t\tOpenAI is thinking...".into());
 
-    let movie = fetch_movie_details(&movie_id, config_data.clone()).await?;
-    debug!("{:?}", movie);
-
     let system_message = Message::builder()
         .role(String::from("system"))
         .content(format!(
@@ -71,11 +246,12 @@ async fn ask_question(
 
     let user_message = Message::builder()
         .role(String::from("user"))
-        .content(format!("{}", query_object.question))
+        .content(query_object.question.to_string())
         .build();
 
     let oai_request = ChatCompletionRequest::builder()
-        .model(String::from(config_data.open_ai.model.clone()))
+        .model(config_data.open_ai.model.clone())
+        .sampling(&config_data.sampling.question)
         .message(system_message)
         .message(user_message)
         .build();
@@ -107,121 +283,258 @@ async fn ask_question(
 
     debug!("{}", message);
 
+    if let Some(embedding) = question_embedding {
+        let cache_lock = cache.lock().unwrap();
+        remember_answer(
+            movie_id,
+            AnsweredQuestion {
+                question: query_object.question.clone(),
+                embedding,
+                answer: message.clone(),
+            },
+            &mut cache_lock.answered_questions.lock().unwrap(),
+            config_data.answer_cache.max_per_movie,
+        );
+    }
+
     // Return the response as plain text
     let response = HttpResponse::Ok()
         .insert_header(ContentType(mime::TEXT_PLAIN))
         .body(message);
 
-    return Ok(response);
+    Ok(response)
 }
 
+/// Turns a natural language request into movie search criteria.
+#[utoipa::path(
+    tag = "movies",
+    params(InputObject),
+    responses(
+        (status = 200, description = "The extracted criteria", body = MovieCriteria),
+        (status = 422, description = "The model's criteria were still invalid after one retry", body = Vec<CriteriaError>),
+        (status = 500, description = "The model couldn't be reached", body = String, content_type = "text/plain")
+    ),
+    security((), ("api_key" = []))
+)]
 #[get("/api/movieCriteria")]
 async fn get_movie_criteria(
     input_object: web::Query<InputObject>, // Extract question from query string
     config: web::Data<Config>,
-) -> Result<String, Box<dyn std::error::Error>> {
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
     debug!("Question: {}", input_object.input);
 
     let config_data = config.clone();
 
-    let client = reqwest::Client::new();
-
-    let mut sp = Spinner::new(Spinners::Dots9, "\t\tOpenAI is thinking...".into());
-
     let system_message = Message::builder()
         .role(String::from("system"))
         .content(format!(
-            r#"Please take the user's question to generate a application/json response object with the following format that can be used in an api call:
-            {{
-              "search"?: string, // A keyword search query
-              "genre"?: string, // A genre to filter on. Single value. The genre should be one of: Action, Adventure, Animation, Comedy, Crime, Documentary, Drama, Family, Fantasy, History, Horror, Music, Mystery, Romance, Science Fiction, Thriller, War, Western, TV Movie.
-              "mpaa"?: string, // An MPAA rating to filter on (PG, PG-13, R, etc.)
-              "releaseDateMin"?: string, // The minimum release date to filter on. Format: YYYY-MM-DD
-              "releaseDateMax"?: string, // The maximum release date to filter on. Format: YYYY-MM-DD
-              "scoreMin"?: number, // The minimum vote/score/rating to filter on. 0-10 scale.
-              "scoreMax"?: number // The maximum vote/score/rating to filter on. 0-10 scale.
-            }}
-            "#,
+            "Please take the user's question to generate a application/json response object with the following format that can be used in an api call:\n{}\nToday's date is {}. Use it for relative dates such as \"recent\" or \"from the last ten years\"; decades like \"the 90s\" can be given as 1990s.",
+            describe_criteria_fields(),
+            format_date(today())
         ))
         .build();
 
-    let user_message = Message::builder()
-        .role(String::from("user"))
-        .content(format!("{}", input_object.input))
-        .build();
-
-    let oai_request = ChatCompletionRequest::builder()
-        .model(String::from(config_data.open_ai.model.clone()))
-        .message(system_message)
-        .message(user_message)
-        .response_format(ResponseFormat { type_: JsonObject })
-        .build();
-
-    let body = to_string(&oai_request).unwrap();
-    debug!("Movie Criteria Request: {}", body);
-
-    let prompt_response = client
-        .post(format!(
-            "{}openai/deployments/{}/chat/completions?api-version={}",
-            config_data.open_ai.url, config_data.open_ai.model, config_data.open_ai.api_version
-        ))
-        .header("Content-Type", "application/json")
-        .header("api-key", config_data.open_ai.key.clone())
-        .body(body)
-        .send()
-        .await?;
-
-    sp.stop();
-
-    let response_body = prompt_response.text().await?;
-    debug!("{}", response_body);
-
-    let json: ChatCompletionResponse = from_str(&response_body)?;
-
-    let movie_criteria_response: MovieCriteria =
-        // from_str(&json.choices[0].message.content.to_string())?;
-      from_str(&extract_message(&json))?;
-    debug!("{:?}", movie_criteria_response);
-
-    // let message = json.choices[0].message.content.to_string();
-    let message = extract_message(&json);
+    let mut messages = vec![
+        system_message,
+        Message::builder()
+            .role(String::from("user"))
+            .content(input_object.input.clone())
+            .build(),
+    ];
+
+    // The model gets one chance to fix criteria that fail validation
+    let mut errors = Vec::new();
+    for attempt in 1..=MAX_CRITERIA_ATTEMPTS {
+        let mut oai_request_builder = ChatCompletionRequest::builder()
+            .model(config_data.open_ai.model.clone())
+            .sampling(&config_data.sampling.criteria)
+            .response_format(ResponseFormat::json_schema(
+                String::from("movie_criteria"),
+                strict_criteria_schema(),
+                true,
+            ));
+        for message in messages.iter() {
+            oai_request_builder = oai_request_builder.message(message.clone());
+        }
+        let oai_request = oai_request_builder.build();
+
+        let mut sp = Spinner::new(Spinners::Dots9, "\t\tOpenAI is thinking...".into());
+        let json = send_chat_completion(&oai_request, &config_data).await?;
+        sp.stop();
+
+        let message = extract_message(&json);
+        match parse_criteria(&message) {
+            Ok(movie_criteria) => {
+                debug!("{:?}", movie_criteria);
+                return Ok(HttpResponse::Ok().json(movie_criteria));
+            }
+            Err(validation_errors) => {
+                warn!(
+                    "Invalid movie criteria on attempt {}: {:?}",
+                    attempt, validation_errors
+                );
+
+                messages.push(
+                    Message::builder()
+                        .role(String::from("assistant"))
+                        .content(message)
+                        .build(),
+                );
+                messages.push(
+                    Message::builder()
+                        .role(String::from("user"))
+                        .content(format!(
+                            "That JSON has these problems:\n{}\nReply with the corrected JSON object only.",
+                            validation_errors
+                                .iter()
+                                .map(|x| format!("- {}", x))
+                                .collect::<Vec<String>>()
+                                .join("\n")
+                        ))
+                        .build(),
+                );
+                errors = validation_errors;
+            }
+        }
+    }
 
-    return Ok(message);
+    Ok(HttpResponse::UnprocessableEntity().json(errors))
 }
 
+/// Lists the movies whose embeddings are closest to the given movie, with their similarity.
+///
+/// The results can be limited with the same filters as the movie list and a minimum similarity. With
+/// `diversity` set they are re-ranked with maximal marginal relevance, trading some similarity for variety
+/// so sequels and near-clones don't crowd out everything else. The genre and franchise caps apply either
+/// way, and tight caps can leave fewer than `k` results.
+#[utoipa::path(
+    tag = "movies",
+    params(("movie_id" = String, Path, description = "TMDB ID of the movie"), SimilarMoviesQuery),
+    responses(
+        (status = 200, description = "Most similar movies first", body = Vec<SimilarMovie>),
+        (status = 400, description = "An invalid parameter or filter", body = String, content_type = "text/plain"),
+        (status = 500, description = "The data files are missing or nothing similar was found", body = String, content_type = "text/plain")
+    ),
+    security((), ("api_key" = []))
+)]
 #[get("/api/movies/{movie_id}/similar")]
 async fn similar_movies(
     movie_id: web::Path<String>, // Extract movieID from path
+    query: web::Query<SimilarMoviesQuery>,
     cache: web::Data<Mutex<Cache>>,
-) -> Result<String, Box<dyn std::error::Error>> {
-    debug!("Movie ID: {}", movie_id);
+    movie_repository: web::Data<dyn MovieRepository>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    debug!("Movie ID: {}, query: {:?}", movie_id, query);
+
+    let k = query.k.unwrap_or(DEFAULT_SIMILAR_MOVIES);
+    if k == 0 || k > MAX_SIMILAR_MOVIES {
+        return Ok(bad_request(&format!(
+            "k must be between 1 and {}",
+            MAX_SIMILAR_MOVIES
+        )));
+    }
+    if let Some(min_similarity) = query.min_similarity {
+        if !(-1.0..=1.0).contains(&min_similarity) {
+            return Ok(bad_request("min_similarity must be between -1 and 1"));
+        }
+    }
+    if let Some(diversity) = query.diversity {
+        if !(0.0..=1.0).contains(&diversity) {
+            return Ok(bad_request("diversity must be between 0 and 1"));
+        }
+    }
+    if query.max_per_genre == Some(0) || query.max_per_franchise == Some(0) {
+        return Ok(bad_request(
+            "max_per_genre and max_per_franchise must be at least 1",
+        ));
+    }
+    let rerank_options = RerankOptions {
+        diversity: query.diversity,
+        max_per_genre: query.max_per_genre,
+        max_per_franchise: query.max_per_franchise,
+    };
+
+    // The IDs of the movies matching the filters, if there are any
+    let allowed_movie_ids: Option<HashSet<i32>> = if query.has_filters() {
+        let criteria = match validate_criteria(query.criteria()) {
+            Ok(criteria) => criteria,
+            Err(errors) => {
+                let messages: Vec<String> = errors.iter().map(|x| x.to_string()).collect();
+                return Ok(bad_request(&messages.join("; ")));
+            }
+        };
+
+        Some(
+            movie_repository
+                .find_movies(criteria)
+                .await?
+                .iter()
+                .map(|x| x.id)
+                .collect(),
+        )
+    } else {
+        None
+    };
 
-    if can_load_data(&cache) {
+    if load_movie_embeddings(&cache) {
         let mut cosine_similarities = find_similar_movies(
             &movie_id,
             &cache.lock().unwrap().movie_embeddings.lock().unwrap(),
         );
 
-        if cosine_similarities.len() > 0 {
-            cosine_similarities.sort_by(|a, b| b.similarity.partial_cmp(&a.similarity).unwrap());
-
-            let cache_lock = cache.lock().unwrap();
-
-            let top_movies_lock = cache_lock.top_movies.lock().unwrap();
-
-            let similar_movies: Vec<TopRatedMovie> = cosine_similarities
+        if !cosine_similarities.is_empty() {
+            cosine_similarities.retain(|x| {
+                query.min_similarity.is_none_or(|min| x.similarity >= min)
+                    && allowed_movie_ids
+                        .as_ref()
+                        .is_none_or(|ids| ids.contains(&x.movie_id))
+            });
+
+            // Re-ranking needs more candidates than it returns to have something to choose from
+            let pool_size = if rerank_options.is_empty() {
+                k
+            } else {
+                RERANK_POOL_SIZE.max(k * 5)
+            };
+            cosine_similarities.truncate(pool_size);
+
+            let movie_ids: Vec<i32> = cosine_similarities
                 .iter()
-                .take(10)
-                .map(|similarity| {
-                    let movie = top_movies_lock
-                        .iter()
-                        .find(|x| x.id == similarity.movie_id)
-                        .unwrap();
-                    movie.clone()
+                .map(|similarity| similarity.movie_id)
+                .collect();
+
+            let candidate_movies: Vec<TopRatedMovie> =
+                movie_repository.get_movies(&movie_ids).await?;
+
+            let candidates: Vec<RankedMovie> = {
+                let cache_lock = cache.lock().unwrap();
+                let movie_embeddings = cache_lock.movie_embeddings.lock().unwrap();
+
+                candidate_movies
+                    .into_iter()
+                    .filter_map(|movie| {
+                        let similarity = cosine_similarities
+                            .iter()
+                            .find(|x| x.movie_id == movie.id)?
+                            .similarity;
+                        Some(RankedMovie {
+                            embedding: movie_embedding(&movie_embeddings, movie.id)?.to_vec(),
+                            similarity,
+                            movie,
+                        })
+                    })
+                    .collect()
+            };
+
+            let similar_movies: Vec<SimilarMovie> = rerank(candidates, &rerank_options, k)
+                .into_iter()
+                .map(|x| SimilarMovie {
+                    movie: x.movie,
+                    similarity: x.similarity,
                 })
                 .collect();
 
-            Ok(serde_json::to_string(&similar_movies).unwrap())
+            Ok(HttpResponse::Ok().json(similar_movies))
         } else {
             Err(Box::<dyn std::error::Error>::from(
                 "No similar movies found.".to_string(),
@@ -234,94 +547,351 @@ async fn similar_movies(
     }
 }
 
+/// Recommends movies from a set of liked and optionally disliked movies.
+///
+/// The liked movies' embeddings are averaged, the disliked ones are subtracted, and the catalogue is ranked
+/// by similarity to the result. The liked and disliked movies themselves are never recommended.
+#[utoipa::path(
+    tag = "movies",
+    request_body = RecommendationRequest,
+    responses(
+        (status = 200, description = "Best recommendations first", body = Vec<TopRatedMovie>),
+        (status = 400, description = "No usable liked movies or an invalid weight", body = String, content_type = "text/plain"),
+        (status = 500, description = "The embeddings couldn't be read", body = String, content_type = "text/plain")
+    ),
+    security((), ("api_key" = []))
+)]
+#[post("/api/movies/recommendations")]
+async fn recommend_movies(
+    request: web::Json<RecommendationRequest>,
+    cache: web::Data<Mutex<Cache>>,
+    movie_repository: web::Data<dyn MovieRepository>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    debug!("Recommendation request: {:?}", request);
+
+    if request.liked.is_empty() {
+        return Ok(bad_request("At least one liked movie is required"));
+    }
+
+    let disliked = request.disliked.clone().unwrap_or_default();
+    if let Some(movie_id) = request.liked.iter().find(|x| disliked.contains(x)) {
+        return Ok(bad_request(&format!(
+            "Movie {} is both liked and disliked",
+            movie_id
+        )));
+    }
+
+    let disliked_weight = request.disliked_weight.unwrap_or(DEFAULT_DISLIKED_WEIGHT);
+    if !(0.0..=1.0).contains(&disliked_weight) {
+        return Ok(bad_request("disliked_weight must be between 0 and 1"));
+    }
+
+    let limit = request
+        .limit
+        .unwrap_or(DEFAULT_RECOMMENDATIONS)
+        .clamp(1, MAX_RECOMMENDATIONS);
+
+    if !load_movie_embeddings(&cache) {
+        return Err(Box::<dyn std::error::Error>::from(
+            "JSON files not found.".to_string(),
+        ));
+    }
+
+    let cosine_similarities = {
+        let cache_lock = cache.lock().unwrap();
+        let movie_embeddings = cache_lock.movie_embeddings.lock().unwrap();
+
+        let liked_embeddings: Vec<&[f32]> = request
+            .liked
+            .iter()
+            .filter_map(|x| movie_embedding(&movie_embeddings, *x))
+            .collect();
+        if liked_embeddings.is_empty() {
+            return Ok(bad_request("None of the liked movies have an embedding"));
+        }
+        let disliked_embeddings: Vec<&[f32]> = disliked
+            .iter()
+            .filter_map(|x| movie_embedding(&movie_embeddings, *x))
+            .collect();
+        debug!(
+            "Recommending from {} liked and {} disliked embeddings",
+            liked_embeddings.len(),
+            disliked_embeddings.len()
+        );
+
+        let query_vector =
+            match preference_vector(&liked_embeddings, &disliked_embeddings, disliked_weight) {
+                Some(query_vector) => query_vector,
+                None => {
+                    return Ok(bad_request(
+                        "The liked and disliked movies cancel each other out",
+                    ))
+                }
+            };
+
+        rank_by_embedding(&query_vector, &movie_embeddings)
+    };
+
+    let movie_ids: Vec<i32> = cosine_similarities
+        .iter()
+        .map(|x| x.movie_id)
+        .filter(|x| !request.liked.contains(x) && !disliked.contains(x))
+        .take(limit)
+        .collect();
+
+    let recommended_movies = movie_repository.get_movies(&movie_ids).await?;
+
+    Ok(HttpResponse::Ok().json(recommended_movies))
+}
+
+/// Lists the catalogue movies an actor appears in, newest first.
+///
+/// The name is matched loosely, so "hanks" or "Tom Hamks" find Tom Hanks.
+#[utoipa::path(
+    tag = "movies",
+    params(("name" = String, Path, description = "The actor's name")),
+    responses(
+        (status = 200, description = "The actor's movies, possibly none", body = Vec<TopRatedMovie>),
+        (status = 400, description = "The name is too short", body = String, content_type = "text/plain"),
+        (status = 500, description = "The movie source couldn't be read", body = String, content_type = "text/plain")
+    ),
+    security((), ("api_key" = []))
+)]
+#[get("/api/actors/{name}/movies")]
+async fn actor_movies(
+    name: web::Path<String>,
+    movie_repository: web::Data<dyn MovieRepository>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    debug!("Actor: {}", name);
+
+    let criteria = match validate_criteria(
+        MovieCriteria::builder()
+            .cast(vec![name.into_inner()])
+            .build(),
+    ) {
+        Ok(criteria) if criteria.cast.is_some() => criteria,
+        Ok(_) => return Ok(bad_request("An actor's name is required")),
+        Err(errors) => {
+            let messages: Vec<String> = errors.iter().map(|x| x.to_string()).collect();
+            return Ok(bad_request(&messages.join("; ")));
+        }
+    };
+
+    let mut movies = movie_repository.find_movies(criteria).await?;
+    sort_movies(&mut movies, "release_date", &SortDirection::Desc)?;
+    debug!("Found {} movies for the actor", movies.len());
+
+    Ok(HttpResponse::Ok().json(movies))
+}
+
+/// Continues a movie recommendation chat.
+///
+/// The reply is plain text while the assistant is still asking questions, and a JSON list of
+/// movies once it has enough criteria to filter the catalogue. The conversation is kept in a
+/// session cookie, so browser clients on another origin must send requests with
+/// `credentials: "include"`.
+#[utoipa::path(
+    tag = "chat",
+    request_body = MovieChatRequest,
+    responses(
+        (status = 200, description = "The assistant's reply",
+            headers(("X-Session-Id" = String, description = "Conversation ID to send with the next message")),
+            content(
+                (String = "text/plain"),
+                (Vec<TopRatedMovie> = "application/json")
+            )
+        ),
+        (status = 403, description = "The session ID isn't the one in the session cookie", body = String, content_type = "text/plain"),
+        (status = 429, description = "Rate limit exceeded", body = String, content_type = "text/plain",
+            headers(("Retry-After" = u64, description = "Seconds until the next request is allowed"))),
+        (status = 500, description = "The model couldn't be reached or didn't reply", body = String, content_type = "text/plain")
+    ),
+    security((), ("api_key" = []))
+)]
 #[post("/api/movie-chat")]
 async fn movie_chat(
-    chat_messages: web::Json<ChatCompletionRequest>, // conversation from the app
+    chat_request: web::Json<MovieChatRequest>, // new message from the app
+    session: Session,
     config: web::Data<Config>,
     cache: web::Data<Mutex<Cache>>,
+    movie_repository: web::Data<dyn MovieRepository>,
+    conversation_store: web::Data<dyn ConversationStore>,
+    tool_registry: web::Data<ToolRegistry>,
 ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
-    debug!("Chat Messages: {:?}", chat_messages);
+    debug!("Chat Request: {:?}", chat_request);
 
     let config_data = config.clone();
-
-    let client = reqwest::Client::new();
-
-    let mut sp = Spinner::new(Spinners::Dots9, "\t\tOpenAI is thinking...".into());
+    let chat_request = chat_request.into_inner();
+
+    // The conversation is the one in the session cookie, a session ID sent by the client has to match it
+    let session_id = match (
+        chat_request.session_id,
+        session.get::<String>(SESSION_ID_KEY)?,
+    ) {
+        (Some(requested), Some(current)) if requested == current => current,
+        (Some(requested), _) => {
+            warn!("Rejected session ID {} not held by this session", requested);
+            return Ok(HttpResponse::Forbidden()
+                .insert_header(ContentType(mime::TEXT_PLAIN))
+                .body("Session ID doesn't belong to this session."));
+        }
+        (None, Some(current)) => current,
+        (None, None) => Uuid::new_v4().to_string(),
+    };
+    debug!("Session ID: {}", session_id);
+
+    let mut conversation = conversation_store
+        .get(&session_id)?
+        .unwrap_or_else(|| Conversation::new(session_id.clone()));
+
+    conversation.push_message(
+        Message::builder()
+            .role(String::from("user"))
+            .content(chat_request.message)
+            .build(),
+    );
 
     let system_message = Message::builder()
         .role(String::from("system"))
         .content(format!(
             r#"You are an expert movie critic. You will be tasked with providing movie recommendations to someone based on criteria they provide.
             You will need to phish for more information until you think you are ready to answer the question using the movie criteria.
+            You can look movies up with the other tools while you narrow things down.
+            Today's date is {}, use it for relative dates like "recent" or "the last five years".
             "#,
+            format_date(today())
         ))
         .build();
 
-    let mut oai_request_builder = ChatCompletionRequest::builder()
-        .model(config_data.open_ai.model.clone())
-        .tool(return_filter_tool())
-        .response_format(ResponseFormat { type_: Text })
-        .message(system_message);
-
-    // Add existing chat history
-    let chat_messages_inner = chat_messages.into_inner();
-    for message in chat_messages_inner.messages {
-        oai_request_builder = oai_request_builder.message(message);
-    }
-
-    // build final request
-    let oai_request = oai_request_builder.build();
-
-    let body = to_string(&oai_request).unwrap();
-    debug!("Body: {}", body);
-
-    // Call API with prompt and parse response
-    let prompt_response = client
-        .post(format!(
-            "{}openai/deployments/{}/chat/completions?api-version={}",
-            config_data.open_ai.url, config_data.open_ai.model, config_data.open_ai.api_version
-        ))
-        .header("Content-Type", "application/json")
-        .header("api-key", config_data.open_ai.key.clone())
-        .body(body)
-        .send()
-        .await?;
-
-    sp.stop();
-
-    let response_body = prompt_response.text().await?;
-    debug!("Response body: {}", response_body);
+    let filter_tool = return_filter_tool();
+    let tool_definitions = tool_registry.definitions();
+    let fixed_tokens = system_message.estimate_tokens()
+        + estimate_text_tokens(&to_string(&filter_tool)?)
+        + estimate_text_tokens(&to_string(&tool_definitions)?);
+
+    // Add as much of the existing chat history as fits in the context window
+    let history = fit_to_context_window(&mut conversation, fixed_tokens, &config_data).await;
+
+    let tool_context = ToolContext {
+        config: &config_data,
+        cache: &cache,
+        movie_repository: movie_repository.get_ref(),
+    };
+
+    // Tool calls made while answering this message and their results, sent back on the next round
+    let mut tool_messages: Vec<Message> = Vec::new();
+    let mut round = 0;
+    let (json, movie_criteria) = loop {
+        let mut oai_request_builder = ChatCompletionRequest::builder()
+            .model(config_data.open_ai.model.clone())
+            .sampling(&config_data.sampling.chat)
+            .tool(filter_tool.clone())
+            .response_format(ResponseFormat {
+                type_: Text,
+                json_schema: None,
+            })
+            .message(system_message.clone());
+        for tool in tool_definitions.iter() {
+            oai_request_builder = oai_request_builder.tool(tool.clone());
+        }
+        for message in history.iter().chain(tool_messages.iter()) {
+            oai_request_builder = oai_request_builder.message(message.clone());
+        }
+        if round >= MAX_TOOL_ROUNDS {
+            oai_request_builder =
+                oai_request_builder.tool_choice(ToolChoice::Mode(ToolChoiceMode::None));
+        }
+        let oai_request = oai_request_builder.build();
+
+        let mut sp = Spinner::new(Spinners::Dots9, "\t\tOpenAI is thinking...".into());
+        let json = send_chat_completion(&oai_request, &config_data).await?;
+        sp.stop();
+
+        // A valid filter_movies call ends the turn, anything else is answered before asking again
+        let tool_calls = collect_tool_calls(&json.choices);
+        let (filter_calls, lookup_calls): (Vec<&ToolCall>, Vec<&ToolCall>) = tool_calls
+            .iter()
+            .partition(|x| x.function.name.as_deref() == Some(FILTER_TOOL_NAME));
+        let mut filter_results: Vec<(&ToolCall, Result<MovieCriteria, String>)> = filter_calls
+            .into_iter()
+            .map(|call| (call, parse_filter_arguments(call)))
+            .collect();
+
+        // Keep the criteria that passed validation, invalid calls before it don't matter any more
+        if let Some(index) = filter_results.iter().position(|(_, x)| x.is_ok()) {
+            let movie_criteria = filter_results.swap_remove(index).1.ok();
+            break (json, movie_criteria);
+        }
+        if tool_calls.is_empty() || round >= MAX_TOOL_ROUNDS {
+            break (json, None);
+        }
+        round += 1;
 
-    let json: ChatCompletionResponse = from_str(&response_body)?;
+        tool_messages.push(
+            Message::builder()
+                .role(String::from("assistant"))
+                .tool_calls(tool_calls.iter().map(|x| (*x).into()).collect())
+                .build(),
+        );
+        for (call, result) in filter_results {
+            if let Err(e) = result {
+                warn!(
+                    "Tool call {} to {} failed: {}",
+                    call.id, FILTER_TOOL_NAME, e
+                );
+                tool_messages.push(tool_message(&call.id, &tool_error(&e)));
+            }
+        }
+        tool_messages.extend(tool_registry.call_all(&lookup_calls, &tool_context).await);
+    };
     debug!("JSON: {:?}", json);
 
-    // parse response to see what actions to take
-    let message = extract_message(&json);
+    // The criteria are kept in the history as JSON so the model sees what it searched for
+    let message = match &movie_criteria {
+        Some(movie_criteria) => to_string(movie_criteria)?,
+        None => match json.choices.iter().find_map(|x| x.message.content.clone()) {
+            Some(content) => content,
+            None => return Err("The model didn't reply with a message".into()),
+        },
+    };
     debug!("Message: {}", message);
 
-    // Try converting the extracted message to a MovieCriteria object
+    // Remember the reply so the next turn only needs the new user message
+    conversation.push_message(
+        Message::builder()
+            .role(String::from("assistant"))
+            .content(message.clone())
+            .build(),
+    );
+    conversation_store.save(&conversation)?;
+    session.insert(SESSION_ID_KEY, &session_id)?;
+
     // We will filter out any movies that dont meet the filter
-    if let Ok(movie_criteria) = serde_json::from_str::<MovieCriteria>(&message) {
-        if can_load_data(&cache) {
-            let mut possible_movies = cache.lock().unwrap().top_movies.lock().unwrap().to_vec();
-            // Call a function with the MovieCriteria object
-            let possible_movies: Vec<TopRatedMovie> =
-                filter_movies(movie_criteria, possible_movies).await;
+    if let Some(movie_criteria) = movie_criteria {
+        let possible_movies: Vec<TopRatedMovie> =
+            movie_repository.find_movies(movie_criteria).await?;
 
-            let tst: Vec<TopRatedMovie> = possible_movies.iter().take(10).cloned().collect();
+        let tst: Vec<TopRatedMovie> = possible_movies.iter().take(10).cloned().collect();
 
-            let response = HttpResponse::Ok()
-                .insert_header(ContentType(mime::TEXT_PLAIN))
-                .json(tst);
+        let response = HttpResponse::Ok()
+            .insert_header((SESSION_ID_HEADER, session_id))
+            .json(tst);
 
-            return Ok(response);
-        }
+        return Ok(response);
     }
 
     // Return the response as plain text
     let response = HttpResponse::Ok()
         .insert_header(ContentType(mime::TEXT_PLAIN))
+        .insert_header((SESSION_ID_HEADER, session_id))
         .body(message);
 
-    return Ok(response);
+    Ok(response)
+}
+
+// Criteria errors as one message the model can act on
+fn bad_request(message: &str) -> HttpResponse {
+    HttpResponse::BadRequest()
+        .insert_header(ContentType(mime::TEXT_PLAIN))
+        .body(message.to_string())
 }
@@ -1,17 +1,33 @@
+use crate::model::cache::Cache;
 use crate::model::config::Config;
-use crate::model::embedding_request_body::EmbeddingRequestBody;
-use crate::model::movies::movie::TopRatedMovie;
+use crate::model::movies::movie::{Cast, TopRatedMovie};
 use crate::model::movies::movie_embedding::MovieEmbedding;
+use crate::store::movie_repository::MovieRepository;
+use crate::store::sqlite_movie_store::{ImportReport, SqliteMovieRepository};
+use crate::util::cast_helper::take_cast;
+use crate::util::embedding_helper::create_embeddings;
+use crate::util::movie_helper::load_movie_embeddings;
+use crate::util::search_index_helper::{create_or_update_movie_index, upload_movies};
 use actix_web::http::header::ContentType;
-use actix_web::{get, web, HttpResponse, Result};
+use actix_web::{get, post, web, HttpResponse, Result};
 use log::{debug, error, info, warn};
-use openai_api_rs::v1::embedding::EmbeddingResponse;
 use spinners::{Spinner, Spinners};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufReader, ErrorKind, Write};
 use std::io::{Read, Seek};
-
+use std::sync::Mutex;
+
+/// Embeds every catalogue movie that doesn't have an embedding yet. Requires an admin key.
+#[utoipa::path(
+    tag = "admin",
+    responses(
+        (status = 200, description = "All movies are embedded"),
+        (status = 401, description = "Missing or invalid API key", body = String, content_type = "text/plain"),
+        (status = 403, description = "The API key isn't an admin key", body = String, content_type = "text/plain")
+    ),
+    security(("api_key" = []))
+)]
 #[get("/api/embed_movie_json")]
 async fn embed_movie_json(
     config: web::Data<Config>,
@@ -37,39 +53,7 @@ async fn embed_movie_json(
         let input: Vec<String> = generate_inputs(movie)?;
         // debug!("input: {:?}", input);
 
-        let embedding_request = EmbeddingRequestBody::builder()
-            .input(input)
-            .model(Some("text-embedding-3-large".to_string()))
-            .dimensions(Some(1024))
-            .user(Some("ah-scraper".to_string()))
-            .build();
-        debug!("embedding_request: {:?}", embedding_request);
-
-        let body = serde_json::to_string(&embedding_request).unwrap();
-        // debug!("body: {}", body);
-
-        let mut sp = Spinner::new(Spinners::Dots9, "\t\tOpenAI is thinking...".into());
-
-        let client = reqwest::Client::new();
-        let result = client
-            .post(format!(
-                "{}openai/deployments/text-embedding-3-large/embeddings?api-version={}",
-                config_data.open_ai.url, config_data.open_ai.api_version
-            ))
-            .header("Content-Type", "application/json")
-            .header("api-key", config_data.open_ai.key.clone())
-            .body(body)
-            .send()
-            .await?;
-        // debug!("result: {:?}", result);
-
-        sp.stop();
-
-        let response_body = result.text().await?;
-        debug!("Response Body: {}", response_body);
-
-        // Serialize the EmbeddingResponse into a JSON string
-        let embedding_data: EmbeddingResponse = serde_json::from_str(&response_body)?;
+        let embedding_data = create_embeddings(input, &config_data).await?;
 
         let movie_embedding = MovieEmbedding::builder()
             .movie_id(movie_id)
@@ -95,6 +79,132 @@ async fn embed_movie_json(
     return Ok(response);
 }
 
+/// Imports the catalogue, cast and embeddings from src/data into the SQLite database. Requires an admin key.
+#[utoipa::path(
+    tag = "admin",
+    responses(
+        (status = 200, description = "Counts of imported rows and any records that were skipped"),
+        (status = 401, description = "Missing or invalid API key", body = String, content_type = "text/plain"),
+        (status = 403, description = "The API key isn't an admin key", body = String, content_type = "text/plain")
+    ),
+    security(("api_key" = []))
+)]
+#[post("/api/import_movie_db")]
+async fn import_movie_db(
+    config: web::Data<Config>,
+    cache: web::Data<Mutex<Cache>>,
+    movie_repository: web::Data<dyn MovieRepository>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let mut report = ImportReport::default();
+
+    let mut movies: Vec<(TopRatedMovie, Vec<Cast>)> = Vec::new();
+    for top_rated_movie in read_top_rated_movies("src/data/topRatedMovies.json")? {
+        let movie_json_path = format!("src/data/movies/{}.json", top_rated_movie.id);
+
+        // The detail file has the overview, keywords, budget and revenue; fall back to the list entry without it
+        let movie = match read_movie_json(&movie_json_path) {
+            Ok(movie) => movie,
+            Err(err) => {
+                warn!("Failed to read {}: {}", movie_json_path, err);
+                top_rated_movie
+            }
+        };
+        let cast = match read_movie_cast(&movie_json_path) {
+            Ok(cast) => cast,
+            Err(err) => {
+                warn!("Failed to read cast for movie {}: {}", movie.id, err);
+                report.failures.push(format!("{}: {}", movie.id, err));
+                Vec::new()
+            }
+        };
+        movies.push((movie, cast));
+    }
+    let movie_embeddings = read_embedded_movies("src/data/embeddings.json")?;
+
+    // Import through the running repository when it is the SQLite one so its cast index and the cached embeddings
+    // are refreshed, otherwise prepare the database for when the config switches to it
+    match movie_repository.import_catalogue(&movies, &movie_embeddings, &mut report) {
+        Some(result) => {
+            result?;
+            if !movie_embeddings.is_empty() {
+                let cache_lock = cache.lock().unwrap();
+                *cache_lock.movie_embeddings.lock().unwrap() = movie_embeddings;
+            }
+        }
+        None => {
+            let repository =
+                SqliteMovieRepository::open(std::path::Path::new(&config.sqlite.path))?;
+            repository.import_movies(&movies, &mut report)?;
+            repository.import_embeddings(&movie_embeddings, &mut report)?;
+        }
+    }
+
+    info!(
+        "Imported {} movies and {} embeddings into {} with {} failures",
+        report.movies_imported,
+        report.embeddings_imported,
+        config.sqlite.path,
+        report.failures.len()
+    );
+
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// Creates or updates the idx-movies index and uploads the local catalogue with its embeddings. Requires an admin key.
+#[utoipa::path(
+    tag = "admin",
+    responses(
+        (status = 200, description = "Counts of indexed documents and the documents that failed"),
+        (status = 401, description = "Missing or invalid API key", body = String, content_type = "text/plain"),
+        (status = 403, description = "The API key isn't an admin key", body = String, content_type = "text/plain")
+    ),
+    security(("api_key" = []))
+)]
+#[post("/api/index_movies")]
+async fn index_movies(
+    config: web::Data<Config>,
+    cache: web::Data<Mutex<Cache>>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    create_or_update_movie_index(&config.azure_search).await?;
+
+    let mut movies: Vec<TopRatedMovie> = Vec::new();
+    for top_rated_movie in read_top_rated_movies("src/data/topRatedMovies.json")? {
+        let movie_json_path = format!("src/data/movies/{}.json", top_rated_movie.id);
+
+        // The detail file has the overview, keywords and cast; fall back to the list entry without it
+        let movie = match read_movie_json(&movie_json_path) {
+            Ok(mut movie) => {
+                movie.cast = read_movie_cast(&movie_json_path).ok();
+                movie
+            }
+            Err(err) => {
+                warn!("Failed to read {}: {}", movie_json_path, err);
+                top_rated_movie
+            }
+        };
+        movies.push(movie);
+    }
+
+    let mut embeddings: HashMap<i32, Vec<f32>> = HashMap::new();
+    if load_movie_embeddings(&cache) {
+        let cache_lock = cache.lock().unwrap();
+        let movie_embeddings = cache_lock.movie_embeddings.lock().unwrap();
+        for movie_embedding in movie_embeddings.iter() {
+            if let Some(embedding) = movie_embedding
+                .embeddings
+                .as_ref()
+                .and_then(|x| x.data.first())
+            {
+                embeddings.insert(movie_embedding.movie_id, embedding.embedding.clone());
+            }
+        }
+    }
+
+    let report = upload_movies(&config.azure_search, movies, &embeddings).await?;
+
+    Ok(HttpResponse::Ok().json(report))
+}
+
 fn read_top_rated_movies(
     file_path: &str,
 ) -> Result<Vec<TopRatedMovie>, Box<dyn std::error::Error>> {
@@ -113,6 +223,14 @@ fn read_movie_json(file_path: &str) -> Result<TopRatedMovie, Box<dyn std::error:
     Ok(movie)
 }
 
+fn read_movie_cast(file_path: &str) -> Result<Vec<Cast>, Box<dyn std::error::Error>> {
+    debug!("Reading file: {}", file_path);
+    let file = File::open(file_path)?;
+    let reader = BufReader::new(file);
+    let mut movie: serde_json::Value = serde_json::from_reader(reader)?;
+    Ok(take_cast(&mut movie)?)
+}
+
 fn read_embedded_movies(
     file_path: &str,
 ) -> Result<Vec<MovieEmbedding>, Box<dyn std::error::Error>> {
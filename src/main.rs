@@ -1,17 +1,43 @@
 mod api;
+mod middleware;
 mod model;
+mod store;
+mod tools;
 mod util;
 
 use actix_cors::Cors;
+use actix_session::{storage::CookieSessionStore, SessionMiddleware};
+use actix_web::cookie::{Key, SameSite};
 use actix_web::web::Data;
-use actix_web::{http::header, middleware::Logger, web, App, HttpServer};
-use api::movies::{ask_question, get_movie_criteria, movie_chat, similar_movies};
-use api::scraper::embed_movie_json;
+use actix_web::{
+    http::header,
+    middleware::{from_fn, Logger},
+    web, App, HttpServer,
+};
+use api::conversations::{delete_conversation, get_conversation, list_conversations};
+use api::docs::ApiDoc;
+use api::health::{healthz, readyz};
+use api::movies::{
+    actor_movies, ask_question, get_movie, get_movie_criteria, list_movies, movie_chat,
+    recommend_movies, similar_movies,
+};
+use api::scraper::{embed_movie_json, import_movie_db, index_movies};
 use log::debug;
+use std::collections::HashMap;
 use std::sync::Mutex;
 use std::{fs::File, io::Read};
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
 
+use crate::middleware::auth::{authenticate, Authenticator};
+use crate::middleware::rate_limiter::{rate_limit, RateLimiter};
 use crate::model::cache::Cache;
+use crate::model::config::CookieSameSite;
+use crate::model::conversation::SESSION_ID_HEADER;
+use crate::model::movies::cast_index::CastIndex;
+use crate::store::conversation_store::{create_conversation_store, ConversationStore};
+use crate::store::movie_repository::{create_movie_repository, MovieRepository};
+use crate::tools::ToolRegistry;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -31,33 +57,94 @@ async fn main() -> std::io::Result<()> {
     let cache = Data::new(Mutex::new(Cache {
         movie_embeddings: Mutex::new(Vec::new()), // You can initialize this with actual data if available
         top_movies: Mutex::new(Vec::new()), // You can initialize this with actual data if available
+        answered_questions: Mutex::new(HashMap::new()),
+        movie_details: Mutex::new(HashMap::new()),
+        cast_index: Mutex::new(CastIndex::new()),
     }));
 
+    let movie_repository: Data<dyn MovieRepository> =
+        Data::from(create_movie_repository(&config, Data::clone(&cache)));
+
+    let conversation_store: Data<dyn ConversationStore> =
+        Data::from(create_conversation_store(&config.session));
+
+    let tool_registry = Data::new(ToolRegistry::with_movie_tools());
+
+    let authenticator = Data::new(Authenticator::new(config.auth.clone()));
+    let rate_limiter = Data::new(RateLimiter::new(config.rate_limit.clone()));
+
+    let session_key = match &config.session.key {
+        Some(key) => Key::try_from(key.as_bytes()).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "session.key in config.yaml must be at least 64 bytes: {}",
+                    e
+                ),
+            )
+        })?,
+        None => Key::generate(),
+    };
+
     debug!("{:?}", config);
 
     HttpServer::new(move || {
         let cors = Cors::default()
             .allowed_origin(&config.front_end_url) // For development
-            .allowed_methods(vec!["GET", "POST"])
+            .allowed_methods(vec!["GET", "POST", "DELETE"])
             .allowed_headers(vec![
                 header::AUTHORIZATION,
                 header::ACCEPT,
                 header::CONTENT_TYPE,
             ])
+            .expose_headers(vec![SESSION_ID_HEADER])
+            .supports_credentials() // The session cookie is sent cross-origin
             .max_age(3600);
 
         let logger = Logger::default();
         App::new()
+            .wrap(from_fn(rate_limit))
+            .wrap(from_fn(authenticate))
             .wrap(logger)
+            .wrap(
+                SessionMiddleware::builder(CookieSessionStore::default(), session_key.clone())
+                    .cookie_same_site(match config.session.cookie_same_site {
+                        CookieSameSite::Strict => SameSite::Strict,
+                        CookieSameSite::Lax => SameSite::Lax,
+                        CookieSameSite::None => SameSite::None,
+                    })
+                    .cookie_secure(config.session.cookie_secure)
+                    .build(),
+            )
             .wrap(cors)
             .app_data(web::JsonConfig::default().limit(4096)) // <- limit size of the payload (global configuration)
             .app_data(Data::new(config.clone()))
             .app_data(Data::clone(&cache))
+            .app_data(Data::clone(&movie_repository))
+            .app_data(Data::clone(&conversation_store))
+            .app_data(Data::clone(&tool_registry))
+            .app_data(Data::clone(&authenticator))
+            .app_data(Data::clone(&rate_limiter))
+            .service(list_movies)
+            .service(get_movie)
             .service(ask_question)
             .service(get_movie_criteria)
             .service(embed_movie_json)
+            .service(import_movie_db)
+            .service(index_movies)
             .service(similar_movies)
+            .service(recommend_movies)
+            .service(actor_movies)
             .service(movie_chat)
+            .service(list_conversations)
+            .service(get_conversation)
+            .service(delete_conversation)
+            .service(healthz)
+            .service(readyz)
+            .service(
+                SwaggerUi::new("/swagger-ui/{_:.*}")
+                    .url("/api-docs/openapi.json", ApiDoc::openapi()),
+            )
     })
     .bind(("127.0.0.1", 8080))?
     .run()
@@ -0,0 +1,122 @@
+use crate::model::config::{ApiKeyConfig, ApiKeyScope, AuthConfig};
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, ContentType};
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpMessage, HttpResponse};
+use log::{debug, warn};
+use sha2::{Digest, Sha256};
+
+// Routes that spend quota or rewrite data files
+const ADMIN_ROUTES: &[&str] = &[
+    "/api/embed_movie_json",
+    "/api/import_movie_db",
+    "/api/index_movies",
+];
+
+// Identity of the API key that authenticated the request, stored in the request extensions
+#[derive(Debug, Clone)]
+pub struct ApiKeyIdentity {
+    pub name: String,
+    pub scope: ApiKeyScope,
+}
+
+pub struct Authenticator {
+    config: AuthConfig,
+}
+
+impl Authenticator {
+    pub fn new(config: AuthConfig) -> Self {
+        Authenticator { config }
+    }
+
+    fn find_key(&self, api_key: &str) -> Option<&ApiKeyConfig> {
+        let key_hash = hex::encode(Sha256::digest(api_key.as_bytes()));
+
+        self.config
+            .api_keys
+            .iter()
+            .find(|x| x.key_sha256.eq_ignore_ascii_case(&key_hash))
+    }
+}
+
+fn bearer_token(req: &ServiceRequest) -> Option<String> {
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.strip_prefix("Bearer "))
+        .map(|x| x.trim().to_string())
+}
+
+fn reject(
+    req: ServiceRequest,
+    mut response: actix_web::HttpResponseBuilder,
+    message: &str,
+) -> ServiceResponse {
+    let response = response
+        .insert_header(ContentType(mime::TEXT_PLAIN))
+        .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+        .body(message.to_string());
+
+    req.into_response(response)
+}
+
+pub async fn authenticate(
+    authenticator: web::Data<Authenticator>,
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let identity = match bearer_token(&req) {
+        Some(api_key) => match authenticator.find_key(&api_key) {
+            Some(key_config) => Some(ApiKeyIdentity {
+                name: key_config.name.clone(),
+                scope: key_config.scope.clone(),
+            }),
+            None => {
+                warn!("Rejected unknown API key for {}", req.path());
+                return Ok(
+                    reject(req, HttpResponse::Unauthorized(), "Invalid API key.")
+                        .map_into_right_body(),
+                );
+            }
+        },
+        None => None,
+    };
+
+    let is_admin_route = req
+        .match_pattern()
+        .is_some_and(|pattern| ADMIN_ROUTES.contains(&pattern.as_str()));
+
+    if is_admin_route {
+        match &identity {
+            Some(identity) if matches!(identity.scope, ApiKeyScope::Admin) => {}
+            Some(identity) => {
+                warn!("API key {} is not allowed on {}", identity.name, req.path());
+                return Ok(
+                    reject(req, HttpResponse::Forbidden(), "Admin API key required.")
+                        .map_into_right_body(),
+                );
+            }
+            None => {
+                return Ok(
+                    reject(req, HttpResponse::Unauthorized(), "Admin API key required.")
+                        .map_into_right_body(),
+                )
+            }
+        }
+    } else if identity.is_none()
+        && authenticator.config.require_api_key
+        && req.path().starts_with("/api/")
+    {
+        return Ok(
+            reject(req, HttpResponse::Unauthorized(), "API key required.").map_into_right_body(),
+        );
+    }
+
+    if let Some(identity) = identity {
+        debug!("Authenticated as {}", identity.name);
+        req.extensions_mut().insert(identity);
+    }
+
+    Ok(next.call(req).await?.map_into_left_body())
+}
@@ -0,0 +1,163 @@
+use crate::middleware::auth::ApiKeyIdentity;
+use crate::model::config::{RateLimitConfig, RateLimitGroup};
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, ContentType};
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpMessage, HttpResponse};
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Once this many buckets exist, buckets that have refilled completely are dropped
+const MAX_IDLE_BUCKETS: usize = 10_000;
+// How often the idle buckets are looked for, so a full table isn't scanned on every request
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+struct Buckets {
+    // Keyed by group name and client
+    by_client: HashMap<(String, String), TokenBucket>,
+    last_sweep: Instant,
+}
+
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        RateLimiter {
+            config,
+            buckets: Mutex::new(Buckets {
+                by_client: HashMap::new(),
+                last_sweep: Instant::now(),
+            }),
+        }
+    }
+
+    fn find_group(&self, pattern: &str) -> Option<&RateLimitGroup> {
+        self.config
+            .groups
+            .iter()
+            .find(|group| group.routes.iter().any(|route| route == pattern))
+    }
+
+    /**
+     * Takes a token from the client's bucket for the group.
+     *
+     * @returns `Ok(())` when the request may continue, or `Err(seconds)` until the next token is available.
+     */
+    fn try_acquire(&self, group: &RateLimitGroup, client_key: &str) -> Result<(), u64> {
+        let refill_per_second = group.refill_per_minute / 60.0;
+        let capacity = group.capacity as f64;
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.by_client.len() > MAX_IDLE_BUCKETS
+            && now.duration_since(buckets.last_sweep) >= SWEEP_INTERVAL
+        {
+            buckets.last_sweep = now;
+            buckets.by_client.retain(|(group_name, _), bucket| {
+                // Each bucket is full at its own group's capacity, not the one being requested
+                match self.config.groups.iter().find(|x| &x.name == group_name) {
+                    Some(bucket_group) => {
+                        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+                        bucket.tokens + elapsed * bucket_group.refill_per_minute / 60.0
+                            < bucket_group.capacity as f64
+                    }
+                    None => false,
+                }
+            });
+            debug!(
+                "{} rate limit buckets kept after the sweep",
+                buckets.by_client.len()
+            );
+        }
+
+        let bucket = buckets
+            .by_client
+            .entry((group.name.clone(), client_key.to_string()))
+            .or_insert(TokenBucket {
+                tokens: capacity,
+                last_refill: now,
+            });
+
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_second).min(capacity);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else if refill_per_second > 0.0 {
+            Err(((1.0 - bucket.tokens) / refill_per_second).ceil() as u64)
+        } else {
+            Err(60)
+        }
+    }
+
+    fn client_key(&self, req: &ServiceRequest) -> String {
+        // Only keys that passed authentication get their own bucket
+        if let Some(identity) = req.extensions().get::<ApiKeyIdentity>() {
+            return format!("key:{}", identity.name);
+        }
+
+        let ip = if self.config.trust_forwarded_for {
+            req.connection_info()
+                .realip_remote_addr()
+                .map(|x| x.to_string())
+        } else {
+            req.peer_addr().map(|x| x.ip().to_string())
+        };
+
+        format!("ip:{}", ip.unwrap_or_else(|| String::from("unknown")))
+    }
+}
+
+pub async fn rate_limit(
+    rate_limiter: web::Data<RateLimiter>,
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    if !rate_limiter.config.enabled {
+        return Ok(next.call(req).await?.map_into_left_body());
+    }
+
+    let group = match req.match_pattern() {
+        Some(pattern) => rate_limiter.find_group(&pattern),
+        None => None,
+    };
+
+    if let Some(group) = group {
+        let client_key = rate_limiter.client_key(&req);
+
+        if let Err(retry_after) = rate_limiter.try_acquire(group, &client_key) {
+            warn!(
+                "Rate limit exceeded for {} on {}, retry after {}s",
+                client_key, group.name, retry_after
+            );
+
+            let response = HttpResponse::TooManyRequests()
+                .insert_header(ContentType(mime::TEXT_PLAIN))
+                .insert_header((header::RETRY_AFTER, retry_after.to_string()))
+                .body("Too many requests.");
+
+            return Ok(req.into_response(response).map_into_right_body());
+        }
+
+        debug!(
+            "Rate limit token taken for {} on {}",
+            client_key, group.name
+        );
+    }
+
+    Ok(next.call(req).await?.map_into_left_body())
+}
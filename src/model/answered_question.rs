@@ -0,0 +1,6 @@
+#[derive(Debug, Clone)]
+pub struct AnsweredQuestion {
+    pub question: String,
+    pub embedding: Vec<f32>,
+    pub answer: String,
+}
@@ -1,8 +1,17 @@
+use super::answered_question::AnsweredQuestion;
 use super::movies::movie::TopRatedMovie;
+use crate::model::movies::cast_index::CastIndex;
 use crate::model::movies::movie_embedding::MovieEmbedding;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Mutex;
 
 pub struct Cache {
     pub movie_embeddings: Mutex<Vec<MovieEmbedding>>,
     pub top_movies: Mutex<Vec<TopRatedMovie>>,
+    // Answers to askQuestion keyed by movie ID, oldest first
+    pub answered_questions: Mutex<HashMap<i32, VecDeque<AnsweredQuestion>>>,
+    // Full movie records from src/data/movies, loaded on first request
+    pub movie_details: Mutex<HashMap<i32, TopRatedMovie>>,
+    // Cast names from src/data/movies, built the first time a cast filter runs
+    pub cast_index: Mutex<CastIndex>,
 }
@@ -1,6 +1,13 @@
+use crate::model::config::SamplingParameters;
 use serde::{Deserialize, Serialize};
+use tiktoken_rs::cl100k_base_singleton;
+use utoipa::ToSchema;
 
-#[derive(Serialize, Deserialize, Debug)]
+// Every message is wrapped in <|start|>{role/name}\n{content}<|end|>\n
+const TOKENS_PER_MESSAGE: usize = 3;
+const TOKENS_PER_NAME: usize = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct ToolCall {
     pub id: String,
     #[serde(rename = "type")]
@@ -8,7 +15,7 @@ pub struct ToolCall {
     pub function: ToolCallFunction,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct ToolCallFunction {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
@@ -16,7 +23,7 @@ pub struct ToolCallFunction {
     pub arguments: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct Message {
     pub role: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -25,12 +32,48 @@ pub struct Message {
     pub name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<ToolCall>>,
+    // Set on "tool" messages to the ID of the call they answer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
 }
 
 impl Message {
     pub fn builder() -> MessageBuilder {
         MessageBuilder::new()
     }
+
+    // Estimates how many prompt tokens this message costs, using the cl100k tokenizer
+    pub fn estimate_tokens(&self) -> usize {
+        let bpe = cl100k_base_singleton();
+        let bpe = bpe.lock();
+
+        let mut tokens = TOKENS_PER_MESSAGE + bpe.encode_with_special_tokens(&self.role).len();
+
+        if let Some(content) = &self.content {
+            tokens += bpe.encode_with_special_tokens(content).len();
+        }
+
+        if let Some(name) = &self.name {
+            tokens += TOKENS_PER_NAME + bpe.encode_with_special_tokens(name).len();
+        }
+
+        if let Some(tool_call_id) = &self.tool_call_id {
+            tokens += bpe.encode_with_special_tokens(tool_call_id).len();
+        }
+
+        if let Some(tool_calls) = &self.tool_calls {
+            for tool_call in tool_calls {
+                if let Some(name) = &tool_call.function.name {
+                    tokens += bpe.encode_with_special_tokens(name).len();
+                }
+                if let Some(arguments) = &tool_call.function.arguments {
+                    tokens += bpe.encode_with_special_tokens(arguments).len();
+                }
+            }
+        }
+
+        tokens
+    }
 }
 
 pub struct MessageBuilder {
@@ -38,6 +81,7 @@ pub struct MessageBuilder {
     content: Option<String>,
     name: Option<String>,
     tool_calls: Option<Vec<ToolCall>>,
+    tool_call_id: Option<String>,
 }
 
 impl MessageBuilder {
@@ -47,6 +91,7 @@ impl MessageBuilder {
             content: None,
             name: None,
             tool_calls: None,
+            tool_call_id: None,
         }
     }
 
@@ -65,30 +110,95 @@ impl MessageBuilder {
         self
     }
 
+    pub fn tool_calls(mut self, tool_calls: Vec<ToolCall>) -> Self {
+        self.tool_calls = Some(tool_calls);
+        self
+    }
+
+    pub fn tool_call_id(mut self, tool_call_id: String) -> Self {
+        self.tool_call_id = Some(tool_call_id);
+        self
+    }
+
     pub fn build(self) -> Message {
         Message {
             role: self.role.expect("Role is required for UserMessage"),
             content: self.content,
             name: self.name,
             tool_calls: self.tool_calls,
+            tool_call_id: self.tool_call_id,
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ResponseType {
     Text,
     JsonObject,
+    // Structured Outputs, constrained to `ResponseFormat.json_schema`
+    JsonSchema,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct ResponseFormat {
     #[serde(rename = "type")]
     pub type_: ResponseType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub json_schema: Option<JsonSchemaFormat>,
+}
+
+impl ResponseFormat {
+    pub fn json_schema(name: String, schema: serde_json::Value, strict: bool) -> Self {
+        ResponseFormat {
+            type_: ResponseType::JsonSchema,
+            json_schema: Some(JsonSchemaFormat {
+                name,
+                schema,
+                strict,
+            }),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct JsonSchemaFormat {
+    pub name: String,
+    #[schema(value_type = Object)]
+    pub schema: serde_json::Value,
+    // With strict set the model can only produce output matching the schema
+    pub strict: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolChoiceMode {
+    None,
+    Auto,
+    Required,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct NamedToolChoice {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub function: NamedToolChoiceFunction,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct NamedToolChoiceFunction {
+    pub name: String,
+}
+
+// Either "none" | "auto" | "required" or a specific function the model has to call
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(untagged)]
+pub enum ToolChoice {
+    Mode(ToolChoiceMode),
+    Function(NamedToolChoice),
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct ChatCompletionRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub model: Option<String>,
@@ -97,6 +207,20 @@ pub struct ChatCompletionRequest {
     pub response_format: Option<ResponseFormat>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<RequestTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
 }
 
 impl ChatCompletionRequest {
@@ -110,6 +234,13 @@ pub struct ChatCompletionRequestBuilder {
     messages: Vec<Message>,
     response_format: Option<ResponseFormat>,
     tools: Option<Vec<RequestTool>>,
+    tool_choice: Option<ToolChoice>,
+    temperature: Option<f32>,
+    top_p: Option<f32>,
+    max_tokens: Option<u32>,
+    seed: Option<i64>,
+    stop: Option<Vec<String>>,
+    n: Option<u32>,
 }
 
 impl ChatCompletionRequestBuilder {
@@ -119,6 +250,13 @@ impl ChatCompletionRequestBuilder {
             messages: Vec::new(),
             response_format: None,
             tools: None,
+            tool_choice: None,
+            temperature: None,
+            top_p: None,
+            max_tokens: None,
+            seed: None,
+            stop: None,
+            n: None,
         }
     }
     pub fn model(mut self, model: String) -> Self {
@@ -146,20 +284,94 @@ impl ChatCompletionRequestBuilder {
         self
     }
 
+    pub fn tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.tool_choice = Some(tool_choice);
+        self
+    }
+
+    pub fn temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn top_p(mut self, top_p: f32) -> Self {
+        self.top_p = Some(top_p);
+        self
+    }
+
+    pub fn max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn seed(mut self, seed: i64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn stop(mut self, stop: String) -> Self {
+        if self.stop.is_none() {
+            self.stop = Some(Vec::new());
+        }
+        if let Some(ref mut stops) = self.stop {
+            stops.push(stop);
+        }
+        self
+    }
+
+    pub fn n(mut self, n: u32) -> Self {
+        self.n = Some(n);
+        self
+    }
+
+    // Applies every parameter set in the endpoint's sampling config, leaving the rest untouched
+    pub fn sampling(mut self, sampling: &SamplingParameters) -> Self {
+        if let Some(temperature) = sampling.temperature {
+            self = self.temperature(temperature);
+        }
+        if let Some(top_p) = sampling.top_p {
+            self = self.top_p(top_p);
+        }
+        if let Some(max_tokens) = sampling.max_tokens {
+            self = self.max_tokens(max_tokens);
+        }
+        if let Some(seed) = sampling.seed {
+            self = self.seed(seed);
+        }
+        for stop in sampling.stop.iter().flatten() {
+            self = self.stop(stop.clone());
+        }
+        if let Some(n) = sampling.n {
+            self = self.n(n);
+        }
+        if let Some(tool_choice) = &sampling.tool_choice {
+            self = self.tool_choice(tool_choice.clone());
+        }
+        self
+    }
+
     pub fn build(self) -> ChatCompletionRequest {
         ChatCompletionRequest {
             model: self.model,
             messages: self.messages,
             response_format: self.response_format,
             tools: self.tools,
+            tool_choice: self.tool_choice,
+            temperature: self.temperature,
+            top_p: self.top_p,
+            max_tokens: self.max_tokens,
+            seed: self.seed,
+            stop: self.stop,
+            n: self.n,
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct ToolFunction {
     description: Option<String>,
     name: String,
+    #[schema(value_type = Option<Object>)]
     parameters: Option<serde_json::Value>,
 }
 
@@ -208,7 +420,7 @@ impl ToolFunctionBuilder {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct RequestTool {
     #[serde(rename = "type")]
     _type: String,
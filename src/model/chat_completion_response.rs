@@ -66,6 +66,20 @@ pub struct ToolCallFunction {
     pub arguments: Option<String>,
 }
 
+// Echoed back in the assistant message that precedes the tool results
+impl From<&ToolCall> for super::chat_completion_request::ToolCall {
+    fn from(tool_call: &ToolCall) -> Self {
+        super::chat_completion_request::ToolCall {
+            id: tool_call.id.clone(),
+            _type: tool_call._type.clone(),
+            function: super::chat_completion_request::ToolCallFunction {
+                name: tool_call.function.name.clone(),
+                arguments: tool_call.function.arguments.clone(),
+            },
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum FinishReason {
     stop,
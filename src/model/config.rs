@@ -1,3 +1,4 @@
+use crate::model::chat_completion_request::ToolChoice;
 use serde::Deserialize;
 
 #[derive(Deserialize, Debug, Clone)]
@@ -5,6 +6,47 @@ pub struct Config {
     pub front_end_url: String,
     pub azure_search: AzureSearchConfig,
     pub open_ai: OpenAiConfig,
+    #[serde(default)]
+    pub answer_cache: AnswerCacheConfig,
+    #[serde(default)]
+    pub session: SessionConfig,
+    #[serde(default)]
+    pub context_window: ContextWindowConfig,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    // Where movies are read from: local (src/data), azure_search (idx-movies) or sqlite
+    #[serde(default)]
+    pub movie_repository: MovieRepositoryType,
+    #[serde(default)]
+    pub sqlite: SqliteConfig,
+    #[serde(default)]
+    pub sampling: SamplingConfig,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MovieRepositoryType {
+    #[default]
+    Local,
+    AzureSearch,
+    Sqlite,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SqliteConfig {
+    // Database file, created and migrated on startup
+    pub path: String,
+}
+
+impl Default for SqliteConfig {
+    fn default() -> Self {
+        SqliteConfig {
+            path: String::from("src/data/movies.db"),
+        }
+    }
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -21,3 +63,217 @@ pub struct OpenAiConfig {
     pub key: String,
     pub model: String,
 }
+
+// Chat completion parameters per endpoint. Unset values fall back to the deployment's defaults.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SamplingConfig {
+    // movieCriteria, kept deterministic so the same input gives the same filters
+    pub criteria: SamplingParameters,
+    // movie-chat
+    pub chat: SamplingParameters,
+    // askQuestion
+    pub question: SamplingParameters,
+    // Conversation summaries written when trimming the context window
+    pub summary: SamplingParameters,
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        SamplingConfig {
+            criteria: SamplingParameters {
+                temperature: Some(0.0),
+                seed: Some(42),
+                ..SamplingParameters::default()
+            },
+            chat: SamplingParameters {
+                temperature: Some(0.9),
+                ..SamplingParameters::default()
+            },
+            question: SamplingParameters {
+                temperature: Some(0.7),
+                ..SamplingParameters::default()
+            },
+            summary: SamplingParameters {
+                temperature: Some(0.2),
+                max_tokens: Some(300),
+                ..SamplingParameters::default()
+            },
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct SamplingParameters {
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub seed: Option<i64>,
+    pub stop: Option<Vec<String>>,
+    pub n: Option<u32>,
+    pub tool_choice: Option<ToolChoice>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AnswerCacheConfig {
+    pub enabled: bool,
+    // Minimum cosine similarity between two questions for a stored answer to be reused
+    pub similarity_threshold: f32,
+    // Answers kept for each movie, the oldest are dropped first
+    pub max_per_movie: usize,
+}
+
+impl Default for AnswerCacheConfig {
+    fn default() -> Self {
+        AnswerCacheConfig {
+            enabled: true,
+            similarity_threshold: 0.9,
+            max_per_movie: 50,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversationStoreType {
+    Memory,
+    File,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SessionConfig {
+    pub store: ConversationStoreType,
+    // Where the file store keeps one JSON file per conversation
+    pub directory: String,
+    // Cookie signing key, at least 64 bytes. A random key is generated when missing.
+    pub key: Option<String>,
+    // Use none when the front end is on another site, browsers then also require cookie_secure
+    #[serde(default)]
+    pub cookie_same_site: CookieSameSite,
+    // Only send the cookie over HTTPS
+    #[serde(default = "default_cookie_secure")]
+    pub cookie_secure: bool,
+}
+
+fn default_cookie_secure() -> bool {
+    true
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CookieSameSite {
+    Strict,
+    #[default]
+    Lax,
+    None,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        SessionConfig {
+            store: ConversationStoreType::Memory,
+            directory: String::from("src/data/conversations"),
+            key: None,
+            cookie_same_site: CookieSameSite::Lax,
+            cookie_secure: true,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextStrategy {
+    // Drop the oldest turns
+    Trim,
+    // Replace the oldest turns with a model-written summary
+    Summarize,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ContextWindowConfig {
+    // Context window of the chat deployment
+    pub max_tokens: usize,
+    // Tokens left free for the model's reply
+    pub reserved_completion_tokens: usize,
+    pub strategy: ContextStrategy,
+}
+
+impl Default for ContextWindowConfig {
+    fn default() -> Self {
+        ContextWindowConfig {
+            max_tokens: 8192,
+            reserved_completion_tokens: 1024,
+            strategy: ContextStrategy::Trim,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    // Use X-Forwarded-For/Forwarded to find the client IP. Only enable behind a trusted proxy.
+    #[serde(default)]
+    pub trust_forwarded_for: bool,
+    pub groups: Vec<RateLimitGroup>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RateLimitGroup {
+    pub name: String,
+    // Route patterns as registered, e.g. /api/movies/{movie_id}/askQuestion
+    pub routes: Vec<String>,
+    // Burst size
+    pub capacity: u32,
+    pub refill_per_minute: f64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            enabled: true,
+            trust_forwarded_for: false,
+            groups: vec![
+                RateLimitGroup {
+                    name: String::from("chat"),
+                    routes: vec![String::from("/api/movie-chat")],
+                    capacity: 10,
+                    refill_per_minute: 10.0,
+                },
+                RateLimitGroup {
+                    name: String::from("completions"),
+                    routes: vec![
+                        String::from("/api/movieCriteria"),
+                        String::from("/api/movies/{movie_id}/askQuestion"),
+                    ],
+                    capacity: 20,
+                    refill_per_minute: 20.0,
+                },
+            ],
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct AuthConfig {
+    // Reject /api requests without a valid key. Admin routes always need an admin key.
+    #[serde(default)]
+    pub require_api_key: bool,
+    #[serde(default)]
+    pub api_keys: Vec<ApiKeyConfig>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ApiKeyConfig {
+    pub name: String,
+    // Hex SHA-256 of the key, e.g. `printf '%s' "$KEY" | sha256sum`
+    pub key_sha256: String,
+    pub scope: ApiKeyScope,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyScope {
+    User,
+    Admin,
+}
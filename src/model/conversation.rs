@@ -0,0 +1,75 @@
+use super::chat_completion_request::Message;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+// Returned on every chat response so clients know which conversation they are in
+pub const SESSION_ID_HEADER: &str = "X-Session-Id";
+// Key under which the conversation ID is kept in the session cookie
+pub const SESSION_ID_KEY: &str = "conversation_id";
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct Conversation {
+    pub id: String,
+    pub created_at: String,
+    pub updated_at: String,
+    pub messages: Vec<Message>,
+    // Stands in for the oldest messages once the conversation outgrows the context window
+    #[serde(default)]
+    pub history_summary: Option<HistorySummary>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct HistorySummary {
+    pub content: String,
+    // How many of the oldest messages the summary covers
+    pub message_count: usize,
+}
+
+impl Conversation {
+    pub fn new(id: String) -> Self {
+        let now = Utc::now().to_rfc3339();
+        Conversation {
+            id,
+            created_at: now.clone(),
+            updated_at: now,
+            messages: Vec::new(),
+            history_summary: None,
+        }
+    }
+
+    pub fn push_message(&mut self, message: Message) {
+        self.messages.push(message);
+        self.updated_at = Utc::now().to_rfc3339();
+    }
+
+    pub fn summary(&self) -> ConversationSummary {
+        ConversationSummary {
+            id: self.id.clone(),
+            created_at: self.created_at.clone(),
+            updated_at: self.updated_at.clone(),
+            message_count: self.messages.len(),
+            title: self
+                .messages
+                .iter()
+                .find(|x| x.role == "user")
+                .and_then(|x| x.content.clone()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ConversationSummary {
+    pub id: String,
+    pub created_at: String,
+    pub updated_at: String,
+    pub message_count: usize,
+    pub title: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct MovieChatRequest {
+    // Has to match the conversation in the session cookie
+    pub session_id: Option<String>,
+    pub message: String,
+}
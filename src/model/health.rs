@@ -0,0 +1,34 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct HealthReport {
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<DataCoverage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dependencies: Option<Vec<DependencyStatus>>,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct DataCoverage {
+    pub catalogue_loaded: bool,
+    pub embeddings_loaded: bool,
+    pub movie_count: usize,
+    pub embedding_count: usize,
+    pub movies_missing_embeddings: usize,
+    // A few of the IDs counted in movies_missing_embeddings
+    pub missing_embedding_ids: Vec<i32>,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct DependencyStatus {
+    pub name: String,
+    pub url: String,
+    pub reachable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_code: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub latency_ms: u128,
+}
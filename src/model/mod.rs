@@ -1,8 +1,11 @@
+pub mod answered_question;
 pub mod cache;
 pub mod chat_completion_request;
 pub mod chat_completion_response;
 pub mod config;
+pub mod conversation;
 pub mod cosine_similarity;
 pub mod embedding_request_body;
+pub mod health;
 pub mod movies;
 pub mod query;
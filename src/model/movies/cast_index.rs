@@ -0,0 +1,180 @@
+use std::collections::{HashMap, HashSet};
+
+// A cast member and every catalogue movie they appear in
+#[derive(Debug, Clone)]
+pub struct CastMember {
+    pub name: String,
+    pub movie_ids: Vec<i32>,
+}
+
+// Cast members keyed by their normalized name, for looking up actors by a loosely typed name
+#[derive(Debug, Default)]
+pub struct CastIndex {
+    members: HashMap<String, CastMember>,
+}
+
+impl CastIndex {
+    pub fn new() -> Self {
+        CastIndex::default()
+    }
+
+    pub fn insert(&mut self, name: &str, movie_id: i32) {
+        let key = normalize_name(name);
+        if key.is_empty() {
+            return;
+        }
+
+        let member = self.members.entry(key).or_insert_with(|| CastMember {
+            name: name.to_string(),
+            movie_ids: Vec::new(),
+        });
+        if !member.movie_ids.contains(&movie_id) {
+            member.movie_ids.push(movie_id);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /**
+     * Finds the cast members a name refers to.
+     *
+     * An exact match (ignoring case, accents and punctuation) wins. Otherwise every member with a word starting with
+     * each word of the query is returned, and only when there are none, words that are off by a typo or two.
+     *
+     * @param query - The name as the user typed it, e.g. "Tom Hanks", "hanks" or "Tom Hamks".
+     */
+    pub fn find(&self, query: &str) -> Vec<&CastMember> {
+        let key = normalize_name(query);
+        if key.is_empty() {
+            return Vec::new();
+        }
+
+        if let Some(member) = self.members.get(&key) {
+            return vec![member];
+        }
+
+        let query_words: Vec<&str> = key.split(' ').collect();
+        let members = self.find_by_words(&query_words, starts_word);
+        if !members.is_empty() {
+            return members;
+        }
+
+        self.find_by_words(&query_words, within_typos)
+    }
+
+    // Members with a word matching each of the query words
+    fn find_by_words(
+        &self,
+        query_words: &[&str],
+        words_match: fn(&str, &str) -> bool,
+    ) -> Vec<&CastMember> {
+        let mut members: Vec<&CastMember> = self
+            .members
+            .iter()
+            .filter(|(name, _)| {
+                let name_words: Vec<&str> = name.split(' ').collect();
+                query_words
+                    .iter()
+                    .all(|query_word| name_words.iter().any(|x| words_match(query_word, x)))
+            })
+            .map(|(_, member)| member)
+            .collect();
+        members.sort_by(|a, b| a.name.cmp(&b.name));
+
+        members
+    }
+
+    // Movies that every one of the names appears in
+    pub fn movie_ids_with_all(&self, names: &[String]) -> HashSet<i32> {
+        let mut movie_ids: Option<HashSet<i32>> = None;
+
+        for name in names {
+            let ids: HashSet<i32> = self
+                .find(name)
+                .iter()
+                .flat_map(|x| x.movie_ids.iter().copied())
+                .collect();
+            movie_ids = Some(match movie_ids {
+                Some(movie_ids) => movie_ids.intersection(&ids).copied().collect(),
+                None => ids,
+            });
+        }
+
+        movie_ids.unwrap_or_default()
+    }
+}
+
+/**
+ * Lowercases the name, folds common accented letters and drops punctuation, so "Penélope Cruz", "penelope cruz"
+ * and "Penelope  Cruz." compare equal.
+ */
+pub fn normalize_name(name: &str) -> String {
+    let folded: String = name
+        .to_lowercase()
+        .chars()
+        // Apostrophes join the word, "O'Connor" is "oconnor" rather than "o connor"
+        .filter(|c| !matches!(c, '\'' | '’'))
+        .map(fold_accent)
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+
+    folded.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+fn fold_accent(c: char) -> char {
+    match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => 'a',
+        'ç' | 'č' | 'ć' => 'c',
+        'è' | 'é' | 'ê' | 'ë' | 'ě' => 'e',
+        'ì' | 'í' | 'î' | 'ï' => 'i',
+        'ñ' | 'ń' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => 'o',
+        'š' | 'ś' => 's',
+        'ù' | 'ú' | 'û' | 'ü' => 'u',
+        'ý' | 'ÿ' => 'y',
+        'ž' | 'ź' | 'ż' => 'z',
+        _ => c,
+    }
+}
+
+// A query word of three or more letters matches the start of a word, shorter ones the whole word
+fn starts_word(query_word: &str, name_word: &str) -> bool {
+    if query_word.chars().count() >= 3 {
+        name_word.starts_with(query_word)
+    } else {
+        name_word == query_word
+    }
+}
+
+// Longer words may be off by a typo or two
+fn within_typos(query_word: &str, name_word: &str) -> bool {
+    let allowed_edits = match query_word.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    };
+
+    edit_distance(query_word, name_word) <= allowed_edits
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b_chars.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b_chars.len() + 1];
+        for (j, b_char) in b_chars.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[b_chars.len()]
+}
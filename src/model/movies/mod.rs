@@ -1,3 +1,7 @@
+pub mod cast_index;
 pub mod movie;
 pub mod movie_criteria;
 pub mod movie_embedding;
+pub mod movie_page;
+pub mod recommendation_request;
+pub mod similar_movie;
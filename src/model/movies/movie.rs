@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct Movie {
@@ -48,34 +49,34 @@ pub struct Movie {
     imdb_score: f64,
 }
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug, ToSchema)]
 pub struct TopRatedMovie {
     #[allow(dead_code)]
-    backdrop_path: String,
+    pub backdrop_path: String,
     #[allow(dead_code)]
     pub id: i32,
     #[allow(dead_code)]
     pub title: String,
     #[allow(dead_code)]
-    poster_path: String,
+    pub poster_path: String,
     #[allow(dead_code)]
     pub release_date: String,
     #[allow(dead_code)]
-    vote_average: f64,
+    pub vote_average: f64,
     #[allow(dead_code)]
-    vote_count: u32,
+    pub vote_count: u32,
     #[allow(dead_code)]
-    popularity: f64,
+    pub popularity: f64,
     #[allow(dead_code)]
     pub overview: Option<String>,
     #[allow(dead_code)]
-    imdb_id: Option<String>,
+    pub imdb_id: Option<String>,
     #[allow(dead_code)]
-    budget: Option<u32>,
+    pub budget: Option<u32>,
     #[allow(dead_code)]
-    homepage: Option<String>,
+    pub homepage: Option<String>,
     #[allow(dead_code)]
-    revenue: Option<u32>,
+    pub revenue: Option<u32>,
     #[allow(dead_code)]
     pub runtime: u32,
     #[allow(dead_code)]
@@ -83,7 +84,7 @@ pub struct TopRatedMovie {
     #[allow(dead_code)]
     pub genres: Vec<String>,
     #[allow(dead_code)]
-    cast: Option<Vec<Cast>>,
+    pub cast: Option<Vec<Cast>>,
     #[allow(dead_code)]
     pub keywords: Option<Vec<String>>,
     #[allow(dead_code)]
@@ -96,12 +97,74 @@ pub struct TopRatedMovie {
     pub imdb_score: f64,
 }
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
-struct Cast {
+#[derive(Deserialize, Serialize, Clone, Debug, ToSchema)]
+pub struct Cast {
     #[allow(dead_code)]
-    name: String,
+    pub name: String,
     #[allow(dead_code)]
-    character: String,
+    pub character: String,
     #[allow(dead_code)]
-    profile_path: String,
+    pub profile_path: String,
+}
+
+impl TryFrom<Movie> for TopRatedMovie {
+    type Error = std::num::ParseIntError;
+
+    // Azure Search keys are strings, the local catalogue uses TMDB's numeric IDs
+    fn try_from(movie: Movie) -> Result<Self, Self::Error> {
+        Ok(TopRatedMovie {
+            backdrop_path: movie.backdrop_path,
+            id: movie.id.parse()?,
+            title: movie.title,
+            poster_path: movie.poster_path,
+            release_date: movie.release_date,
+            vote_average: movie.vote_average,
+            vote_count: movie.vote_count,
+            popularity: movie.popularity,
+            overview: Some(movie.overview),
+            imdb_id: Some(movie.imdb_id),
+            budget: Some(movie.budget),
+            homepage: Some(movie.homepage),
+            revenue: Some(movie.revenue),
+            runtime: movie.runtime,
+            tagline: Some(movie.tagline),
+            genres: movie.genres,
+            cast: Some(movie.cast),
+            keywords: Some(movie.keywords),
+            mpaa: movie.mpaa,
+            summaries: Some(movie.summaries),
+            synopsis: Some(movie.synopsis),
+            imdb_score: movie.imdb_score,
+        })
+    }
+}
+
+impl From<TopRatedMovie> for Movie {
+    // The index document shape has no optional fields, so missing values become empty
+    fn from(movie: TopRatedMovie) -> Self {
+        Movie {
+            backdrop_path: movie.backdrop_path,
+            id: movie.id.to_string(),
+            title: movie.title,
+            poster_path: movie.poster_path,
+            release_date: movie.release_date,
+            vote_average: movie.vote_average,
+            vote_count: movie.vote_count,
+            popularity: movie.popularity,
+            overview: movie.overview.unwrap_or_default(),
+            imdb_id: movie.imdb_id.unwrap_or_default(),
+            budget: movie.budget.unwrap_or_default(),
+            homepage: movie.homepage.unwrap_or_default(),
+            revenue: movie.revenue.unwrap_or_default(),
+            runtime: movie.runtime,
+            tagline: movie.tagline.unwrap_or_default(),
+            genres: movie.genres,
+            cast: movie.cast.unwrap_or_default(),
+            keywords: movie.keywords.unwrap_or_default(),
+            mpaa: movie.mpaa,
+            summaries: movie.summaries.unwrap_or_default(),
+            synopsis: movie.synopsis.unwrap_or_default(),
+            imdb_score: movie.imdb_score,
+        }
+    }
 }
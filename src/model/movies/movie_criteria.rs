@@ -1,14 +1,58 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct MovieCriteria {
+    /// A keyword search query
     pub search: Option<String>,
+    /// Genres to filter on, comma separated. A movie matches if it has any of them.
     pub genre: Option<String>,
+    /// The movie has at least one of these genres
+    pub genres_any: Option<Vec<String>>,
+    /// The movie has every one of these genres
+    pub genres_all: Option<Vec<String>>,
+    /// The movie has none of these genres
+    pub genres_exclude: Option<Vec<String>>,
+    /// An MPAA rating to filter on
     pub mpaa: Option<String>,
+    /// The movie is rated one of these
+    pub mpaa_any: Option<Vec<String>>,
+    /// The movie isn't rated any of these
+    pub mpaa_exclude: Option<Vec<String>>,
+    /// Actors who all appear in the cast. Names are matched loosely, e.g. "hanks" finds Tom Hanks
+    pub cast: Option<Vec<String>>,
+    /// The earliest release date. YYYY-MM-DD, a year (1994) or a decade (1990s), which starts on its first day
     pub release_date_min: Option<String>,
+    /// The latest release date. YYYY-MM-DD, a year (1994) or a decade (1990s), which ends on its last day
     pub release_date_max: Option<String>,
+    // Validation turns it into release_date_min
+    /// Only movies released in this many years up to today, e.g. 5 for "the last five years"
+    pub released_within_years: Option<u32>,
+    /// The minimum vote/score/rating to filter on. 0-10 scale.
+    #[schema(minimum = 0, maximum = 10)]
     pub score_min: Option<f32>,
+    /// The maximum vote/score/rating to filter on. 0-10 scale.
+    #[schema(minimum = 0, maximum = 10)]
     pub score_max: Option<f32>,
+    /// TMDB keywords the movie has all of, e.g. "time travel" or "based on novel or book"
+    pub keywords: Option<Vec<String>>,
+    /// TMDB keywords the movie has none of
+    pub keywords_exclude: Option<Vec<String>>,
+    /// The minimum production budget in US dollars, e.g. 100000000 for $100M. Movies with an unknown budget are left out.
+    pub budget_min: Option<u64>,
+    /// The maximum production budget in US dollars. Movies with an unknown budget are left out.
+    pub budget_max: Option<u64>,
+    /// The minimum worldwide box office revenue in US dollars. Movies with an unknown revenue are left out.
+    pub revenue_min: Option<u64>,
+    /// The maximum worldwide box office revenue in US dollars. Movies with an unknown revenue are left out.
+    pub revenue_max: Option<u64>,
+    /// The minimum number of TMDB votes, to leave out obscure movies
+    pub vote_count_min: Option<u32>,
+    /// The minimum TMDB popularity. Most movies are between 10 and 100, blockbusters go into the thousands.
+    pub popularity_min: Option<f64>,
+    /// The maximum TMDB popularity, e.g. 30 for lesser-known movies
+    pub popularity_max: Option<f64>,
+    /// The user's request in their own words
     pub natural_language: Option<String>,
 }
 
@@ -16,6 +60,38 @@ impl MovieCriteria {
     pub fn builder() -> MovieCriteriaBuilder {
         MovieCriteriaBuilder::new()
     }
+
+    // Keywords, budget and revenue are only in the detail files, not topRatedMovies.json
+    pub fn needs_details(&self) -> bool {
+        self.keywords.is_some()
+            || self.keywords_exclude.is_some()
+            || self.budget_min.is_some()
+            || self.budget_max.is_some()
+            || self.revenue_min.is_some()
+            || self.revenue_max.is_some()
+    }
+}
+
+// A problem with one field of model-produced criteria
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct CriteriaError {
+    pub field: String,
+    pub message: String,
+}
+
+impl CriteriaError {
+    pub fn new(field: &str, message: String) -> Self {
+        CriteriaError {
+            field: field.to_string(),
+            message,
+        }
+    }
+}
+
+impl std::fmt::Display for CriteriaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,7 +100,7 @@ pub struct SortCriteria {
     pub direction: Option<SortDirection>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum SortDirection {
     Asc,
@@ -41,11 +117,27 @@ pub struct Criteria {
 pub struct MovieCriteriaBuilder {
     search: Option<String>,
     genre: Option<String>,
+    genres_any: Option<Vec<String>>,
+    genres_all: Option<Vec<String>>,
+    genres_exclude: Option<Vec<String>>,
     mpaa: Option<String>,
+    mpaa_any: Option<Vec<String>>,
+    mpaa_exclude: Option<Vec<String>>,
+    cast: Option<Vec<String>>,
     release_date_min: Option<String>,
     release_date_max: Option<String>,
+    released_within_years: Option<u32>,
     score_min: Option<f32>,
     score_max: Option<f32>,
+    keywords: Option<Vec<String>>,
+    keywords_exclude: Option<Vec<String>>,
+    budget_min: Option<u64>,
+    budget_max: Option<u64>,
+    revenue_min: Option<u64>,
+    revenue_max: Option<u64>,
+    vote_count_min: Option<u32>,
+    popularity_min: Option<f64>,
+    popularity_max: Option<f64>,
     natural_language: Option<String>,
 }
 
@@ -54,11 +146,27 @@ impl MovieCriteriaBuilder {
         MovieCriteriaBuilder {
             search: None,
             genre: None,
+            genres_any: None,
+            genres_all: None,
+            genres_exclude: None,
             mpaa: None,
+            mpaa_any: None,
+            mpaa_exclude: None,
+            cast: None,
             release_date_min: None,
             release_date_max: None,
+            released_within_years: None,
             score_min: None,
             score_max: None,
+            keywords: None,
+            keywords_exclude: None,
+            budget_min: None,
+            budget_max: None,
+            revenue_min: None,
+            revenue_max: None,
+            vote_count_min: None,
+            popularity_min: None,
+            popularity_max: None,
             natural_language: None,
         }
     }
@@ -73,11 +181,31 @@ impl MovieCriteriaBuilder {
         self
     }
 
+    pub fn genres_all(mut self, genres_all: Vec<String>) -> Self {
+        self.genres_all = Some(genres_all);
+        self
+    }
+
+    pub fn genres_exclude(mut self, genres_exclude: Vec<String>) -> Self {
+        self.genres_exclude = Some(genres_exclude);
+        self
+    }
+
     pub fn mpaa(mut self, mpaa: String) -> Self {
         self.mpaa = Some(mpaa);
         self
     }
 
+    pub fn mpaa_exclude(mut self, mpaa_exclude: Vec<String>) -> Self {
+        self.mpaa_exclude = Some(mpaa_exclude);
+        self
+    }
+
+    pub fn cast(mut self, cast: Vec<String>) -> Self {
+        self.cast = Some(cast);
+        self
+    }
+
     pub fn release_date_min(mut self, release_date_min: String) -> Self {
         self.release_date_min = Some(release_date_min);
         self
@@ -98,6 +226,51 @@ impl MovieCriteriaBuilder {
         self
     }
 
+    pub fn keywords(mut self, keywords: Vec<String>) -> Self {
+        self.keywords = Some(keywords);
+        self
+    }
+
+    pub fn keywords_exclude(mut self, keywords_exclude: Vec<String>) -> Self {
+        self.keywords_exclude = Some(keywords_exclude);
+        self
+    }
+
+    pub fn budget_min(mut self, budget_min: u64) -> Self {
+        self.budget_min = Some(budget_min);
+        self
+    }
+
+    pub fn budget_max(mut self, budget_max: u64) -> Self {
+        self.budget_max = Some(budget_max);
+        self
+    }
+
+    pub fn revenue_min(mut self, revenue_min: u64) -> Self {
+        self.revenue_min = Some(revenue_min);
+        self
+    }
+
+    pub fn revenue_max(mut self, revenue_max: u64) -> Self {
+        self.revenue_max = Some(revenue_max);
+        self
+    }
+
+    pub fn vote_count_min(mut self, vote_count_min: u32) -> Self {
+        self.vote_count_min = Some(vote_count_min);
+        self
+    }
+
+    pub fn popularity_min(mut self, popularity_min: f64) -> Self {
+        self.popularity_min = Some(popularity_min);
+        self
+    }
+
+    pub fn popularity_max(mut self, popularity_max: f64) -> Self {
+        self.popularity_max = Some(popularity_max);
+        self
+    }
+
     pub fn natural_language(mut self, natural_language: String) -> Self {
         self.natural_language = Some(natural_language);
         self
@@ -107,11 +280,27 @@ impl MovieCriteriaBuilder {
         MovieCriteria {
             search: self.search,
             genre: self.genre,
+            genres_any: self.genres_any,
+            genres_all: self.genres_all,
+            genres_exclude: self.genres_exclude,
             mpaa: self.mpaa,
+            mpaa_any: self.mpaa_any,
+            mpaa_exclude: self.mpaa_exclude,
+            cast: self.cast,
             release_date_min: self.release_date_min,
             release_date_max: self.release_date_max,
+            released_within_years: self.released_within_years,
             score_min: self.score_min,
             score_max: self.score_max,
+            keywords: self.keywords,
+            keywords_exclude: self.keywords_exclude,
+            budget_min: self.budget_min,
+            budget_max: self.budget_max,
+            revenue_min: self.revenue_min,
+            revenue_max: self.revenue_max,
+            vote_count_min: self.vote_count_min,
+            popularity_min: self.popularity_min,
+            popularity_max: self.popularity_max,
             natural_language: self.natural_language,
         }
     }
@@ -0,0 +1,12 @@
+use super::movie::TopRatedMovie;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct MoviePage {
+    pub items: Vec<TopRatedMovie>,
+    pub page: usize,
+    pub page_size: usize,
+    // Number of movies matching the filters across all pages
+    pub total: usize,
+}
@@ -0,0 +1,15 @@
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct RecommendationRequest {
+    /// TMDB IDs of movies the user liked
+    pub liked: Vec<i32>,
+    /// TMDB IDs of movies the user disliked, steering recommendations away from them
+    pub disliked: Option<Vec<i32>>,
+    /// How strongly the disliked movies push results away, from 0 to 1. Defaults to 0.5.
+    #[schema(minimum = 0, maximum = 1)]
+    pub disliked_weight: Option<f32>,
+    /// Number of recommendations, at most 50. Defaults to 10.
+    pub limit: Option<usize>,
+}
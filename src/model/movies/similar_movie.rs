@@ -0,0 +1,11 @@
+use super::movie::TopRatedMovie;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct SimilarMovie {
+    #[serde(flatten)]
+    pub movie: TopRatedMovie,
+    /// Cosine similarity to the movie the results are similar to, from -1 to 1
+    pub similarity: f32,
+}
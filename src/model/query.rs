@@ -1,11 +1,219 @@
+use super::movies::movie_criteria::{MovieCriteria, SortDirection};
 use serde::{Deserialize, Serialize};
+use utoipa::IntoParams;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct QuestionObject {
     pub question: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct InputObject {
     pub input: String,
 }
+
+#[derive(Serialize, Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MovieListQuery {
+    /// Case-insensitive part of the title
+    pub title: Option<String>,
+    /// Comma separated genres, any of which must match
+    pub genre: Option<String>,
+    /// Comma separated genres, all of which must match
+    pub genres_all: Option<String>,
+    /// Comma separated genres, none of which may match
+    pub genres_exclude: Option<String>,
+    pub mpaa: Option<String>,
+    /// Comma separated MPAA ratings to leave out
+    pub mpaa_exclude: Option<String>,
+    /// Comma separated actor names, all of which must be in the cast
+    pub cast: Option<String>,
+    /// YYYY-MM-DD, a year (1994) or a decade (1990s)
+    pub release_date_min: Option<String>,
+    /// YYYY-MM-DD, a year (1994) or a decade (1990s)
+    pub release_date_max: Option<String>,
+    /// IMDb score, 0-10
+    pub score_min: Option<f32>,
+    /// IMDb score, 0-10
+    pub score_max: Option<f32>,
+    /// Comma separated TMDB keywords, all of which must match
+    pub keywords: Option<String>,
+    /// Comma separated TMDB keywords, none of which may match
+    pub keywords_exclude: Option<String>,
+    /// US dollars
+    pub budget_min: Option<u64>,
+    /// US dollars
+    pub budget_max: Option<u64>,
+    /// US dollars
+    pub revenue_min: Option<u64>,
+    /// US dollars
+    pub revenue_max: Option<u64>,
+    pub vote_count_min: Option<u32>,
+    pub popularity_min: Option<f64>,
+    pub popularity_max: Option<f64>,
+    /// One of title, release_date, imdb_score, vote_average, popularity or runtime
+    pub sort: Option<String>,
+    pub direction: Option<SortDirection>,
+    /// Starts at 1
+    pub page: Option<usize>,
+    pub page_size: Option<usize>,
+}
+
+impl MovieListQuery {
+    pub fn has_filters(&self) -> bool {
+        self.genre.is_some()
+            || self.genres_all.is_some()
+            || self.genres_exclude.is_some()
+            || self.mpaa.is_some()
+            || self.mpaa_exclude.is_some()
+            || self.cast.is_some()
+            || self.release_date_min.is_some()
+            || self.release_date_max.is_some()
+            || self.score_min.is_some()
+            || self.score_max.is_some()
+            || self.keywords.is_some()
+            || self.keywords_exclude.is_some()
+            || self.budget_min.is_some()
+            || self.budget_max.is_some()
+            || self.revenue_min.is_some()
+            || self.revenue_max.is_some()
+            || self.vote_count_min.is_some()
+            || self.popularity_min.is_some()
+            || self.popularity_max.is_some()
+    }
+
+    pub fn criteria(&self) -> MovieCriteria {
+        let mut builder = MovieCriteria::builder();
+        if let Some(genre) = &self.genre {
+            builder = builder.genre(genre.clone());
+        }
+        if let Some(genres_all) = split_list(&self.genres_all) {
+            builder = builder.genres_all(genres_all);
+        }
+        if let Some(genres_exclude) = split_list(&self.genres_exclude) {
+            builder = builder.genres_exclude(genres_exclude);
+        }
+        if let Some(mpaa) = &self.mpaa {
+            builder = builder.mpaa(mpaa.clone());
+        }
+        if let Some(mpaa_exclude) = split_list(&self.mpaa_exclude) {
+            builder = builder.mpaa_exclude(mpaa_exclude);
+        }
+        if let Some(cast) = split_list(&self.cast) {
+            builder = builder.cast(cast);
+        }
+        if let Some(release_date_min) = &self.release_date_min {
+            builder = builder.release_date_min(release_date_min.clone());
+        }
+        if let Some(release_date_max) = &self.release_date_max {
+            builder = builder.release_date_max(release_date_max.clone());
+        }
+        if let Some(score_min) = self.score_min {
+            builder = builder.score_min(score_min);
+        }
+        if let Some(score_max) = self.score_max {
+            builder = builder.score_max(score_max);
+        }
+        if let Some(keywords) = split_list(&self.keywords) {
+            builder = builder.keywords(keywords);
+        }
+        if let Some(keywords_exclude) = split_list(&self.keywords_exclude) {
+            builder = builder.keywords_exclude(keywords_exclude);
+        }
+        if let Some(budget_min) = self.budget_min {
+            builder = builder.budget_min(budget_min);
+        }
+        if let Some(budget_max) = self.budget_max {
+            builder = builder.budget_max(budget_max);
+        }
+        if let Some(revenue_min) = self.revenue_min {
+            builder = builder.revenue_min(revenue_min);
+        }
+        if let Some(revenue_max) = self.revenue_max {
+            builder = builder.revenue_max(revenue_max);
+        }
+        if let Some(vote_count_min) = self.vote_count_min {
+            builder = builder.vote_count_min(vote_count_min);
+        }
+        if let Some(popularity_min) = self.popularity_min {
+            builder = builder.popularity_min(popularity_min);
+        }
+        if let Some(popularity_max) = self.popularity_max {
+            builder = builder.popularity_max(popularity_max);
+        }
+
+        builder.build()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SimilarMoviesQuery {
+    /// Number of results, at most 100. Defaults to 10.
+    pub k: Option<usize>,
+    /// Leave out movies less similar than this, from -1 to 1
+    pub min_similarity: Option<f32>,
+    /// Comma separated genres, any of which must match
+    pub genre: Option<String>,
+    pub mpaa: Option<String>,
+    /// YYYY-MM-DD, a year (1994) or a decade (1990s)
+    pub release_date_min: Option<String>,
+    /// YYYY-MM-DD, a year (1994) or a decade (1990s)
+    pub release_date_max: Option<String>,
+    /// IMDb score, 0-10
+    pub score_min: Option<f32>,
+    /// IMDb score, 0-10
+    pub score_max: Option<f32>,
+    /// Trade-off between similarity (0) and variety among the results (1). Leave out to rank by similarity only.
+    pub diversity: Option<f32>,
+    /// At most this many results with the same main genre
+    pub max_per_genre: Option<usize>,
+    /// At most this many results from one franchise, guessed from the first words of the title
+    pub max_per_franchise: Option<usize>,
+}
+
+impl SimilarMoviesQuery {
+    pub fn has_filters(&self) -> bool {
+        self.genre.is_some()
+            || self.mpaa.is_some()
+            || self.release_date_min.is_some()
+            || self.release_date_max.is_some()
+            || self.score_min.is_some()
+            || self.score_max.is_some()
+    }
+
+    pub fn criteria(&self) -> MovieCriteria {
+        let mut builder = MovieCriteria::builder();
+        if let Some(genre) = &self.genre {
+            builder = builder.genre(genre.clone());
+        }
+        if let Some(mpaa) = &self.mpaa {
+            builder = builder.mpaa(mpaa.clone());
+        }
+        if let Some(release_date_min) = &self.release_date_min {
+            builder = builder.release_date_min(release_date_min.clone());
+        }
+        if let Some(release_date_max) = &self.release_date_max {
+            builder = builder.release_date_max(release_date_max.clone());
+        }
+        if let Some(score_min) = self.score_min {
+            builder = builder.score_min(score_min);
+        }
+        if let Some(score_max) = self.score_max {
+            builder = builder.score_max(score_max);
+        }
+
+        builder.build()
+    }
+}
+
+fn split_list(value: &Option<String>) -> Option<Vec<String>> {
+    value.as_ref().map(|x| {
+        x.split(',')
+            .map(|x| x.trim().to_string())
+            .filter(|x| !x.is_empty())
+            .collect()
+    })
+}
@@ -0,0 +1,151 @@
+use crate::model::config::{ConversationStoreType, SessionConfig};
+use crate::model::conversation::Conversation;
+use log::debug;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, ErrorKind};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+pub trait ConversationStore: Send + Sync {
+    fn get(&self, id: &str) -> Result<Option<Conversation>, Box<dyn std::error::Error>>;
+    fn save(&self, conversation: &Conversation) -> Result<(), Box<dyn std::error::Error>>;
+    fn list(&self) -> Result<Vec<Conversation>, Box<dyn std::error::Error>>;
+    fn delete(&self, id: &str) -> Result<bool, Box<dyn std::error::Error>>;
+}
+
+pub fn create_conversation_store(config: &SessionConfig) -> Arc<dyn ConversationStore> {
+    match config.store {
+        ConversationStoreType::Memory => Arc::new(InMemoryConversationStore::new()),
+        ConversationStoreType::File => {
+            Arc::new(FileConversationStore::new(PathBuf::from(&config.directory)))
+        }
+    }
+}
+
+pub struct InMemoryConversationStore {
+    conversations: Mutex<HashMap<String, Conversation>>,
+}
+
+impl InMemoryConversationStore {
+    pub fn new() -> Self {
+        InMemoryConversationStore {
+            conversations: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl ConversationStore for InMemoryConversationStore {
+    fn get(&self, id: &str) -> Result<Option<Conversation>, Box<dyn std::error::Error>> {
+        Ok(self.conversations.lock().unwrap().get(id).cloned())
+    }
+
+    fn save(&self, conversation: &Conversation) -> Result<(), Box<dyn std::error::Error>> {
+        self.conversations
+            .lock()
+            .unwrap()
+            .insert(conversation.id.clone(), conversation.clone());
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<Conversation>, Box<dyn std::error::Error>> {
+        Ok(self
+            .conversations
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect())
+    }
+
+    fn delete(&self, id: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        Ok(self.conversations.lock().unwrap().remove(id).is_some())
+    }
+}
+
+// Stores each conversation as `{directory}/{id}.json`
+pub struct FileConversationStore {
+    directory: PathBuf,
+    // Serializes file access between concurrent requests
+    lock: Mutex<()>,
+}
+
+impl FileConversationStore {
+    pub fn new(directory: PathBuf) -> Self {
+        FileConversationStore {
+            directory,
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn conversation_path(&self, id: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        // Session IDs come from clients, so never let them escape the directory
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(Box::<dyn std::error::Error>::from(format!(
+                "Invalid conversation ID: {}",
+                id
+            )));
+        }
+        Ok(self.directory.join(format!("{}.json", id)))
+    }
+}
+
+impl ConversationStore for FileConversationStore {
+    fn get(&self, id: &str) -> Result<Option<Conversation>, Box<dyn std::error::Error>> {
+        let path = self.conversation_path(id)?;
+        let _guard = self.lock.lock().unwrap();
+
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let conversation: Conversation = serde_json::from_reader(BufReader::new(file))?;
+        Ok(Some(conversation))
+    }
+
+    fn save(&self, conversation: &Conversation) -> Result<(), Box<dyn std::error::Error>> {
+        let path = self.conversation_path(&conversation.id)?;
+        let _guard = self.lock.lock().unwrap();
+
+        fs::create_dir_all(&self.directory)?;
+        fs::write(&path, serde_json::to_string(conversation)?)?;
+        debug!("Saved conversation to {:?}", path);
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<Conversation>, Box<dyn std::error::Error>> {
+        let _guard = self.lock.lock().unwrap();
+
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut conversations = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().and_then(|x| x.to_str()) != Some("json") {
+                continue;
+            }
+            let file = File::open(&path)?;
+            match serde_json::from_reader::<_, Conversation>(BufReader::new(file)) {
+                Ok(conversation) => conversations.push(conversation),
+                Err(e) => debug!("Skipping unreadable conversation {:?}: {}", path, e),
+            }
+        }
+        Ok(conversations)
+    }
+
+    fn delete(&self, id: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        let path = self.conversation_path(id)?;
+        let _guard = self.lock.lock().unwrap();
+
+        match fs::remove_file(&path) {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
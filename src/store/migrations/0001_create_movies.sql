@@ -0,0 +1,63 @@
+CREATE TABLE movies (
+    id INTEGER PRIMARY KEY,
+    -- Position in topRatedMovies.json, used as the default ordering
+    catalogue_rank INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    backdrop_path TEXT NOT NULL,
+    poster_path TEXT NOT NULL,
+    release_date TEXT NOT NULL,
+    vote_average REAL NOT NULL,
+    vote_count INTEGER NOT NULL,
+    popularity REAL NOT NULL,
+    overview TEXT,
+    imdb_id TEXT,
+    budget INTEGER,
+    homepage TEXT,
+    revenue INTEGER,
+    runtime INTEGER NOT NULL,
+    tagline TEXT,
+    mpaa TEXT NOT NULL,
+    -- JSON array of strings
+    summaries TEXT,
+    synopsis TEXT,
+    imdb_score REAL NOT NULL
+);
+
+CREATE INDEX idx_movies_release_date ON movies (release_date);
+CREATE INDEX idx_movies_imdb_score ON movies (imdb_score);
+CREATE INDEX idx_movies_mpaa ON movies (mpaa);
+
+CREATE TABLE movie_genres (
+    movie_id INTEGER NOT NULL REFERENCES movies (id) ON DELETE CASCADE,
+    genre TEXT NOT NULL COLLATE NOCASE,
+    PRIMARY KEY (movie_id, genre)
+);
+
+CREATE INDEX idx_movie_genres_genre ON movie_genres (genre);
+
+CREATE TABLE movie_keywords (
+    movie_id INTEGER NOT NULL REFERENCES movies (id) ON DELETE CASCADE,
+    keyword TEXT NOT NULL COLLATE NOCASE,
+    PRIMARY KEY (movie_id, keyword)
+);
+
+CREATE INDEX idx_movie_keywords_keyword ON movie_keywords (keyword);
+
+CREATE TABLE movie_cast (
+    movie_id INTEGER NOT NULL REFERENCES movies (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    name TEXT NOT NULL COLLATE NOCASE,
+    character TEXT NOT NULL,
+    profile_path TEXT NOT NULL,
+    PRIMARY KEY (movie_id, position)
+);
+
+CREATE INDEX idx_movie_cast_name ON movie_cast (name);
+
+CREATE TABLE movie_embeddings (
+    movie_id INTEGER PRIMARY KEY REFERENCES movies (id) ON DELETE CASCADE,
+    model TEXT NOT NULL,
+    dimensions INTEGER NOT NULL,
+    -- Little-endian f32 values
+    embedding BLOB NOT NULL
+);
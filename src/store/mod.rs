@@ -0,0 +1,3 @@
+pub mod conversation_store;
+pub mod movie_repository;
+pub mod sqlite_movie_store;
@@ -0,0 +1,311 @@
+use crate::model::cache::Cache;
+use crate::model::config::{AzureSearchConfig, Config, MovieRepositoryType};
+use crate::model::movies::movie::{Cast, Movie, TopRatedMovie};
+use crate::model::movies::movie_criteria::MovieCriteria;
+use crate::model::movies::movie_embedding::MovieEmbedding;
+use crate::store::sqlite_movie_store::{ImportReport, SqliteMovieRepository};
+use crate::util::cast_helper::find_cast_movie_ids;
+use crate::util::movie_helper::{filter_movies, load_movie_details, load_top_movies};
+use crate::util::search_query_helper::criteria_to_search_request;
+use actix_web::web::Data;
+use async_trait::async_trait;
+use log::debug;
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+// Azure Search returns at most 1000 documents per request
+const AZURE_SEARCH_PAGE_SIZE: usize = 1000;
+
+#[async_trait(?Send)]
+pub trait MovieRepository: Send + Sync {
+    async fn get_movie(
+        &self,
+        movie_id: i32,
+    ) -> Result<Option<TopRatedMovie>, Box<dyn std::error::Error>>;
+
+    // Returns the movies in the same order as `movie_ids`, skipping unknown IDs
+    async fn get_movies(
+        &self,
+        movie_ids: &[i32],
+    ) -> Result<Vec<TopRatedMovie>, Box<dyn std::error::Error>>;
+
+    async fn list_movies(&self) -> Result<Vec<TopRatedMovie>, Box<dyn std::error::Error>>;
+
+    async fn find_movies(
+        &self,
+        criteria: MovieCriteria,
+    ) -> Result<Vec<TopRatedMovie>, Box<dyn std::error::Error>>;
+
+    /**
+     * Replaces the stored catalogue and embeddings, movies missing from the new catalogue are removed.
+     *
+     * @param movies - The catalogue, each paired with its cast when the detail file had one.
+     * @returns None when the repository reads its movies from elsewhere and has nothing to import into.
+     */
+    fn import_catalogue(
+        &self,
+        _movies: &[(TopRatedMovie, Vec<Cast>)],
+        _movie_embeddings: &[MovieEmbedding],
+        _report: &mut ImportReport,
+    ) -> Option<Result<(), Box<dyn std::error::Error>>> {
+        None
+    }
+}
+
+pub fn create_movie_repository(
+    config: &Config,
+    cache: Data<Mutex<Cache>>,
+) -> Arc<dyn MovieRepository> {
+    match config.movie_repository {
+        MovieRepositoryType::Local => Arc::new(LocalMovieRepository::new(cache)),
+        MovieRepositoryType::AzureSearch => {
+            Arc::new(AzureSearchMovieRepository::new(config.azure_search.clone()))
+        }
+        MovieRepositoryType::Sqlite => {
+            let repository = SqliteMovieRepository::open(Path::new(&config.sqlite.path))
+                .expect("Failed to open the SQLite movie database");
+
+            // Similarity search still runs over the shared cache, so seed it from the database
+            let movie_embeddings = repository
+                .list_embeddings()
+                .expect("Failed to read embeddings from the SQLite movie database");
+            debug!("Loaded {} embeddings from SQLite", movie_embeddings.len());
+            if !movie_embeddings.is_empty() {
+                let cache_lock = cache.lock().unwrap();
+                *cache_lock.movie_embeddings.lock().unwrap() = movie_embeddings;
+            }
+
+            Arc::new(repository)
+        }
+    }
+}
+
+pub(crate) fn order_by_ids(movies: Vec<TopRatedMovie>, movie_ids: &[i32]) -> Vec<TopRatedMovie> {
+    movie_ids
+        .iter()
+        .filter_map(|movie_id| movies.iter().find(|x| x.id == *movie_id).cloned())
+        .collect()
+}
+
+// Reads src/data/topRatedMovies.json and src/data/movies/{id}.json through the shared cache
+pub struct LocalMovieRepository {
+    cache: Data<Mutex<Cache>>,
+}
+
+impl LocalMovieRepository {
+    pub fn new(cache: Data<Mutex<Cache>>) -> Self {
+        LocalMovieRepository { cache }
+    }
+
+    fn top_movies(&self) -> Result<Vec<TopRatedMovie>, Box<dyn std::error::Error>> {
+        if !load_top_movies(&self.cache) {
+            return Err(Box::<dyn std::error::Error>::from(
+                "JSON files not found.".to_string(),
+            ));
+        }
+
+        Ok(self
+            .cache
+            .lock()
+            .unwrap()
+            .top_movies
+            .lock()
+            .unwrap()
+            .to_vec())
+    }
+}
+
+#[async_trait(?Send)]
+impl MovieRepository for LocalMovieRepository {
+    async fn get_movie(
+        &self,
+        movie_id: i32,
+    ) -> Result<Option<TopRatedMovie>, Box<dyn std::error::Error>> {
+        // Prefer the full record, falling back to the summary in topRatedMovies.json
+        if let Some(movie) = load_movie_details(&self.cache, movie_id) {
+            return Ok(Some(movie));
+        }
+
+        Ok(self.top_movies()?.into_iter().find(|x| x.id == movie_id))
+    }
+
+    async fn get_movies(
+        &self,
+        movie_ids: &[i32],
+    ) -> Result<Vec<TopRatedMovie>, Box<dyn std::error::Error>> {
+        Ok(order_by_ids(self.top_movies()?, movie_ids))
+    }
+
+    async fn list_movies(&self) -> Result<Vec<TopRatedMovie>, Box<dyn std::error::Error>> {
+        self.top_movies()
+    }
+
+    async fn find_movies(
+        &self,
+        criteria: MovieCriteria,
+    ) -> Result<Vec<TopRatedMovie>, Box<dyn std::error::Error>> {
+        let mut top_movies = self.top_movies()?;
+
+        // Swap in the full records when filtering on fields only the detail files have
+        if criteria.needs_details() {
+            top_movies = top_movies
+                .into_iter()
+                .map(|x| load_movie_details(&self.cache, x.id).unwrap_or(x))
+                .collect();
+        }
+
+        let cast = criteria.cast.clone();
+        let mut movies = filter_movies(criteria, top_movies).await;
+
+        // topRatedMovies.json has no cast, so match against the index built from the detail files
+        if let Some(cast) = cast {
+            let movie_ids = find_cast_movie_ids(&self.cache, &cast).ok_or_else(|| {
+                Box::<dyn std::error::Error>::from("Movie detail files not found.".to_string())
+            })?;
+            movies.retain(|x| movie_ids.contains(&x.id));
+        }
+
+        Ok(movies)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct SearchResponse {
+    value: Vec<Movie>,
+}
+
+// Reads the idx-movies Azure Cognitive Search index
+pub struct AzureSearchMovieRepository {
+    config: AzureSearchConfig,
+}
+
+impl AzureSearchMovieRepository {
+    pub fn new(config: AzureSearchConfig) -> Self {
+        AzureSearchMovieRepository { config }
+    }
+
+    async fn search(
+        &self,
+        body: serde_json::Value,
+    ) -> Result<Vec<TopRatedMovie>, Box<dyn std::error::Error>> {
+        let client = reqwest::Client::new();
+
+        let search_response = client
+            .post(format!(
+                "{}indexes/idx-movies/docs/search?api-version={}",
+                self.config.url, self.config.api_version
+            ))
+            .header("Content-Type", "application/json")
+            .header("api-key", self.config.key.clone())
+            .body(body.to_string())
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let search_results: SearchResponse = serde_json::from_str(&search_response.text().await?)?;
+
+        let mut movies = Vec::new();
+        for movie in search_results.value {
+            movies.push(TopRatedMovie::try_from(movie)?);
+        }
+
+        Ok(movies)
+    }
+
+    // Pages through every result of the search
+    async fn search_all(
+        &self,
+        mut body: serde_json::Value,
+    ) -> Result<Vec<TopRatedMovie>, Box<dyn std::error::Error>> {
+        let mut movies = Vec::new();
+
+        loop {
+            body["top"] = serde_json::json!(AZURE_SEARCH_PAGE_SIZE);
+            body["skip"] = serde_json::json!(movies.len());
+
+            let page = self.search(body.clone()).await?;
+            let page_len = page.len();
+            movies.extend(page);
+
+            if page_len < AZURE_SEARCH_PAGE_SIZE {
+                break;
+            }
+        }
+
+        Ok(movies)
+    }
+}
+
+#[async_trait(?Send)]
+impl MovieRepository for AzureSearchMovieRepository {
+    async fn get_movie(
+        &self,
+        movie_id: i32,
+    ) -> Result<Option<TopRatedMovie>, Box<dyn std::error::Error>> {
+        let client = reqwest::Client::new();
+
+        // Fetch movie details for movie_id
+        let movie_details_response = client
+            .get(format!(
+                "{}indexes/idx-movies/docs/{}?api-version={}",
+                self.config.url, movie_id, self.config.api_version
+            ))
+            .header("Content-Type", "application/json")
+            .header("api-key", self.config.key.clone())
+            .send()
+            .await?;
+
+        if movie_details_response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let movie_details = movie_details_response.error_for_status()?.text().await?;
+        let movie: Movie = serde_json::from_str(&movie_details)?;
+
+        Ok(Some(TopRatedMovie::try_from(movie)?))
+    }
+
+    async fn get_movies(
+        &self,
+        movie_ids: &[i32],
+    ) -> Result<Vec<TopRatedMovie>, Box<dyn std::error::Error>> {
+        if movie_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let ids: Vec<String> = movie_ids.iter().map(|x| x.to_string()).collect();
+        let movies = self
+            .search(serde_json::json!({
+                "search": "*",
+                "filter": format!("search.in(id, '{}', ',')", ids.join(",")),
+                "top": movie_ids.len(),
+            }))
+            .await?;
+
+        Ok(order_by_ids(movies, movie_ids))
+    }
+
+    async fn list_movies(&self) -> Result<Vec<TopRatedMovie>, Box<dyn std::error::Error>> {
+        let movies = self
+            .search_all(serde_json::json!({
+                "search": "*",
+            }))
+            .await?;
+        debug!("Listed {} movies from Azure Search", movies.len());
+
+        Ok(movies)
+    }
+
+    async fn find_movies(
+        &self,
+        criteria: MovieCriteria,
+    ) -> Result<Vec<TopRatedMovie>, Box<dyn std::error::Error>> {
+        let movies = self
+            .search_all(criteria_to_search_request(&criteria))
+            .await?;
+        debug!("Found {} movies in Azure Search", movies.len());
+
+        Ok(movies)
+    }
+}
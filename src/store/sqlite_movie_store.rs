@@ -0,0 +1,658 @@
+use crate::model::movies::cast_index::CastIndex;
+use crate::model::movies::movie::{Cast, TopRatedMovie};
+use crate::model::movies::movie_criteria::MovieCriteria;
+use crate::model::movies::movie_embedding::MovieEmbedding;
+use crate::store::movie_repository::{order_by_ids, MovieRepository};
+use crate::util::date_helper::{normalize_date_bound, DateBound};
+use async_trait::async_trait;
+use log::{debug, info};
+use openai_api_rs::v1::embedding::{EmbeddingData, EmbeddingResponse, Usage};
+use rusqlite::types::{ToSql, Value};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::Mutex;
+
+// Applied in order; the index + 1 is stored in PRAGMA user_version once applied
+const MIGRATIONS: &[&str] = &[include_str!("migrations/0001_create_movies.sql")];
+
+const MOVIE_COLUMNS: &str = "m.id, m.title, m.backdrop_path, m.poster_path, m.release_date, \
+    m.vote_average, m.vote_count, m.popularity, m.overview, m.imdb_id, m.budget, m.homepage, \
+    m.revenue, m.runtime, m.tagline, m.mpaa, m.summaries, m.synopsis, m.imdb_score";
+
+#[derive(Serialize, Debug, Default)]
+pub struct ImportReport {
+    pub movies_imported: usize,
+    pub cast_members_imported: usize,
+    pub embeddings_imported: usize,
+    pub failures: Vec<String>,
+}
+
+pub struct SqliteMovieRepository {
+    connection: Mutex<Connection>,
+    // Built from movie_cast on the first cast filter and dropped when movies are imported
+    cast_index: Mutex<CastIndex>,
+}
+
+impl SqliteMovieRepository {
+    pub fn open(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut connection = Connection::open(path)?;
+        connection.pragma_update(None, "foreign_keys", "ON")?;
+        migrate(&mut connection)?;
+
+        Ok(SqliteMovieRepository {
+            connection: Mutex::new(connection),
+            cast_index: Mutex::new(CastIndex::new()),
+        })
+    }
+
+    /**
+     * Replaces the stored movies with the given catalogue, keeping the given order as the default ordering.
+     *
+     * @param movies - The catalogue, each paired with its cast when the detail file had one.
+     */
+    pub fn import_movies(
+        &self,
+        movies: &[(TopRatedMovie, Vec<Cast>)],
+        report: &mut ImportReport,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+
+        // Movies missing from the new catalogue go too, their genres, keywords, cast and embeddings cascade
+        transaction.execute("DELETE FROM movies", [])?;
+
+        for (rank, (movie, cast)) in movies.iter().enumerate() {
+            // A movie listed twice keeps its last entry
+            transaction.execute("DELETE FROM movies WHERE id = ?1", params![movie.id])?;
+            transaction.execute(
+                "INSERT INTO movies (id, catalogue_rank, title, backdrop_path, poster_path, \
+                 release_date, vote_average, vote_count, popularity, overview, imdb_id, budget, \
+                 homepage, revenue, runtime, tagline, mpaa, summaries, synopsis, imdb_score) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, \
+                 ?17, ?18, ?19, ?20)",
+                params![
+                    movie.id,
+                    rank as i64,
+                    movie.title,
+                    movie.backdrop_path,
+                    movie.poster_path,
+                    movie.release_date,
+                    movie.vote_average,
+                    movie.vote_count,
+                    movie.popularity,
+                    movie.overview,
+                    movie.imdb_id,
+                    movie.budget,
+                    movie.homepage,
+                    movie.revenue,
+                    movie.runtime,
+                    movie.tagline,
+                    movie.mpaa,
+                    match &movie.summaries {
+                        Some(summaries) => Some(serde_json::to_string(summaries)?),
+                        None => None,
+                    },
+                    movie.synopsis,
+                    movie.imdb_score,
+                ],
+            )?;
+
+            for genre in movie.genres.iter() {
+                transaction.execute(
+                    "INSERT OR IGNORE INTO movie_genres (movie_id, genre) VALUES (?1, ?2)",
+                    params![movie.id, genre],
+                )?;
+            }
+
+            for keyword in movie.keywords.iter().flatten() {
+                transaction.execute(
+                    "INSERT OR IGNORE INTO movie_keywords (movie_id, keyword) VALUES (?1, ?2)",
+                    params![movie.id, keyword],
+                )?;
+            }
+
+            for (position, cast_member) in cast.iter().enumerate() {
+                transaction.execute(
+                    "INSERT INTO movie_cast (movie_id, position, name, character, profile_path) \
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        movie.id,
+                        position as i64,
+                        cast_member.name,
+                        cast_member.character,
+                        cast_member.profile_path,
+                    ],
+                )?;
+            }
+
+            report.movies_imported += 1;
+            report.cast_members_imported += cast.len();
+        }
+
+        transaction.commit()?;
+        drop(connection);
+        info!("Imported {} movies into SQLite", report.movies_imported);
+
+        // The cast changed, rebuild the index on the next cast filter
+        *self.cast_index.lock().unwrap() = CastIndex::new();
+
+        Ok(())
+    }
+
+    pub fn import_embeddings(
+        &self,
+        movie_embeddings: &[MovieEmbedding],
+        report: &mut ImportReport,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+
+        for movie_embedding in movie_embeddings {
+            let embedding_response = match &movie_embedding.embeddings {
+                Some(embeddings) if !embeddings.data.is_empty() => embeddings,
+                _ => {
+                    report
+                        .failures
+                        .push(format!("{}: no embedding data", movie_embedding.movie_id));
+                    continue;
+                }
+            };
+            let embedding = &embedding_response.data[0].embedding;
+
+            let movie_exists: bool = transaction.query_row(
+                "SELECT EXISTS (SELECT 1 FROM movies WHERE id = ?1)",
+                params![movie_embedding.movie_id],
+                |row| row.get(0),
+            )?;
+            if !movie_exists {
+                report.failures.push(format!(
+                    "{}: embedding for a movie not in the catalogue",
+                    movie_embedding.movie_id
+                ));
+                continue;
+            }
+
+            transaction.execute(
+                "INSERT OR REPLACE INTO movie_embeddings (movie_id, model, dimensions, embedding) \
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    movie_embedding.movie_id,
+                    embedding_response.model,
+                    embedding.len() as i64,
+                    embedding_to_blob(embedding),
+                ],
+            )?;
+            report.embeddings_imported += 1;
+        }
+
+        transaction.commit()?;
+        info!(
+            "Imported {} embeddings into SQLite",
+            report.embeddings_imported
+        );
+
+        Ok(())
+    }
+
+    pub fn list_embeddings(&self) -> Result<Vec<MovieEmbedding>, Box<dyn std::error::Error>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement =
+            connection.prepare("SELECT movie_id, model, embedding FROM movie_embeddings")?;
+
+        let rows = statement.query_map([], |row| {
+            let movie_id: i32 = row.get(0)?;
+            let model: String = row.get(1)?;
+            let blob: Vec<u8> = row.get(2)?;
+            Ok((movie_id, model, blob))
+        })?;
+
+        let mut movie_embeddings = Vec::new();
+        for row in rows {
+            let (movie_id, model, blob) = row?;
+
+            // Shaped like the embeddings API response so it can sit next to embeddings.json data
+            let embeddings = EmbeddingResponse {
+                object: String::from("list"),
+                data: vec![EmbeddingData {
+                    object: String::from("embedding"),
+                    embedding: blob_to_embedding(&blob),
+                    index: 0,
+                }],
+                model,
+                usage: Usage {
+                    prompt_tokens: 0,
+                    total_tokens: 0,
+                },
+                headers: None,
+            };
+
+            movie_embeddings.push(
+                MovieEmbedding::builder()
+                    .movie_id(movie_id)
+                    .embeddings(embeddings)
+                    .build(),
+            );
+        }
+
+        Ok(movie_embeddings)
+    }
+
+    // The movies every one of the actors appears in
+    fn find_cast_movie_ids(
+        &self,
+        names: &[String],
+    ) -> Result<HashSet<i32>, Box<dyn std::error::Error>> {
+        let mut cast_index = self.cast_index.lock().unwrap();
+
+        if cast_index.is_empty() {
+            let connection = self.connection.lock().unwrap();
+            let mut statement = connection.prepare("SELECT movie_id, name FROM movie_cast")?;
+            let mut rows = statement.query([])?;
+            while let Some(row) = rows.next()? {
+                let name: String = row.get(1)?;
+                cast_index.insert(&name, row.get(0)?);
+            }
+            debug!("Indexed {} cast members from SQLite", cast_index.len());
+        }
+
+        Ok(cast_index.movie_ids_with_all(names))
+    }
+
+    fn query_movies(
+        &self,
+        where_clause: &str,
+        values: Vec<Box<dyn ToSql>>,
+    ) -> Result<Vec<TopRatedMovie>, Box<dyn std::error::Error>> {
+        let connection = self.connection.lock().unwrap();
+
+        let sql = format!(
+            "SELECT {} FROM movies m {} ORDER BY m.catalogue_rank",
+            MOVIE_COLUMNS, where_clause
+        );
+        debug!("SQL: {}", sql);
+
+        let mut statement = connection.prepare(&sql)?;
+        let mut movies = statement
+            .query_map(params_from_iter(values.iter()), movie_from_row)?
+            .collect::<Result<Vec<TopRatedMovie>, rusqlite::Error>>()?;
+
+        for movie in movies.iter_mut() {
+            load_related(&connection, movie)?;
+        }
+
+        Ok(movies)
+    }
+}
+
+#[async_trait(?Send)]
+impl MovieRepository for SqliteMovieRepository {
+    async fn get_movie(
+        &self,
+        movie_id: i32,
+    ) -> Result<Option<TopRatedMovie>, Box<dyn std::error::Error>> {
+        let connection = self.connection.lock().unwrap();
+
+        let movie = connection
+            .query_row(
+                &format!("SELECT {} FROM movies m WHERE m.id = ?1", MOVIE_COLUMNS),
+                params![movie_id],
+                movie_from_row,
+            )
+            .optional()?;
+
+        match movie {
+            Some(mut movie) => {
+                load_related(&connection, &mut movie)?;
+                Ok(Some(movie))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn get_movies(
+        &self,
+        movie_ids: &[i32],
+    ) -> Result<Vec<TopRatedMovie>, Box<dyn std::error::Error>> {
+        let ids: Vec<Value> = movie_ids.iter().map(|x| Value::from(*x)).collect();
+        let movies = self.query_movies("WHERE m.id IN rarray(?1)", vec![Box::new(Rc::new(ids))])?;
+
+        Ok(order_by_ids(movies, movie_ids))
+    }
+
+    async fn list_movies(&self) -> Result<Vec<TopRatedMovie>, Box<dyn std::error::Error>> {
+        self.query_movies("", Vec::new())
+    }
+
+    async fn find_movies(
+        &self,
+        criteria: MovieCriteria,
+    ) -> Result<Vec<TopRatedMovie>, Box<dyn std::error::Error>> {
+        let cast_movie_ids = match &criteria.cast {
+            Some(cast) => Some(self.find_cast_movie_ids(cast)?),
+            None => None,
+        };
+
+        let (where_clause, values) = criteria_to_sql(&criteria, cast_movie_ids);
+        self.query_movies(&where_clause, values)
+    }
+
+    fn import_catalogue(
+        &self,
+        movies: &[(TopRatedMovie, Vec<Cast>)],
+        movie_embeddings: &[MovieEmbedding],
+        report: &mut ImportReport,
+    ) -> Option<Result<(), Box<dyn std::error::Error>>> {
+        Some(
+            self.import_movies(movies, report)
+                .and_then(|_| self.import_embeddings(movie_embeddings, report)),
+        )
+    }
+}
+
+fn migrate(connection: &mut Connection) -> Result<(), Box<dyn std::error::Error>> {
+    let current_version: usize =
+        connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(current_version) {
+        let version = index + 1;
+        info!("Applying SQLite migration {}", version);
+
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(migration)?;
+        transaction.pragma_update(None, "user_version", version)?;
+        transaction.commit()?;
+    }
+
+    // rarray() is used for `IN` lists
+    rusqlite::vtab::array::load_module(connection)?;
+
+    Ok(())
+}
+
+// Translates the criteria into a WHERE clause with positional parameters. Cast names are matched loosely
+// through the cast index beforehand, so they arrive as the IDs of the matching movies.
+fn criteria_to_sql(
+    criteria: &MovieCriteria,
+    cast_movie_ids: Option<HashSet<i32>>,
+) -> (String, Vec<Box<dyn ToSql>>) {
+    let mut conditions: Vec<String> = Vec::new();
+    let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+
+    if let Some(genre) = &criteria.genre {
+        let genres: Vec<Value> = genre
+            .split(',')
+            .map(|g| g.trim())
+            .filter(|g| !g.is_empty())
+            .map(|g| Value::from(g.to_lowercase()))
+            .collect();
+        if !genres.is_empty() {
+            values.push(Box::new(Rc::new(genres)));
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM movie_genres g WHERE g.movie_id = m.id \
+                 AND lower(g.genre) IN rarray(?{}))",
+                values.len()
+            ));
+        }
+    }
+
+    if let Some(genres_any) = &criteria.genres_any {
+        values.push(Box::new(lowercase_array(genres_any)));
+        conditions.push(format!(
+            "EXISTS (SELECT 1 FROM movie_genres g WHERE g.movie_id = m.id \
+             AND lower(g.genre) IN rarray(?{}))",
+            values.len()
+        ));
+    }
+
+    // Every listed genre has its own row, so counting the matches tells whether all are there
+    if let Some(genres_all) = &criteria.genres_all {
+        values.push(Box::new(lowercase_array(genres_all)));
+        values.push(Box::new(genres_all.len() as i64));
+        conditions.push(format!(
+            "(SELECT COUNT(DISTINCT lower(g.genre)) FROM movie_genres g WHERE g.movie_id = m.id \
+             AND lower(g.genre) IN rarray(?{})) = ?{}",
+            values.len() - 1,
+            values.len()
+        ));
+    }
+
+    if let Some(genres_exclude) = &criteria.genres_exclude {
+        values.push(Box::new(lowercase_array(genres_exclude)));
+        conditions.push(format!(
+            "NOT EXISTS (SELECT 1 FROM movie_genres g WHERE g.movie_id = m.id \
+             AND lower(g.genre) IN rarray(?{}))",
+            values.len()
+        ));
+    }
+
+    if let Some(mpaa) = &criteria.mpaa {
+        values.push(Box::new(mpaa.clone()));
+        conditions.push(format!("m.mpaa = ?{}", values.len()));
+    }
+
+    if let Some(mpaa_any) = &criteria.mpaa_any {
+        values.push(Box::new(text_array(mpaa_any)));
+        conditions.push(format!("m.mpaa IN rarray(?{})", values.len()));
+    }
+
+    if let Some(mpaa_exclude) = &criteria.mpaa_exclude {
+        values.push(Box::new(text_array(mpaa_exclude)));
+        conditions.push(format!("m.mpaa NOT IN rarray(?{})", values.len()));
+    }
+
+    if let Some(cast_movie_ids) = cast_movie_ids {
+        let ids: Vec<Value> = cast_movie_ids.into_iter().map(Value::from).collect();
+        values.push(Box::new(Rc::new(ids)));
+        conditions.push(format!("m.id IN rarray(?{})", values.len()));
+    }
+
+    // Dates are stored as YYYY-MM-DD, so they compare correctly as text once years and decades are expanded
+    if let Some(release_date_min) = &criteria.release_date_min {
+        values.push(Box::new(normalize_date_bound(
+            release_date_min,
+            DateBound::Start,
+        )));
+        conditions.push(format!("m.release_date >= ?{}", values.len()));
+    }
+
+    if let Some(release_date_max) = &criteria.release_date_max {
+        values.push(Box::new(normalize_date_bound(
+            release_date_max,
+            DateBound::End,
+        )));
+        conditions.push(format!("m.release_date <= ?{}", values.len()));
+    }
+
+    if let Some(score_min) = criteria.score_min {
+        values.push(Box::new(score_min));
+        conditions.push(format!("m.imdb_score >= ?{}", values.len()));
+    }
+
+    if let Some(score_max) = criteria.score_max {
+        values.push(Box::new(score_max));
+        conditions.push(format!("m.imdb_score <= ?{}", values.len()));
+    }
+
+    if let Some(keywords) = &criteria.keywords {
+        values.push(Box::new(lowercase_array(keywords)));
+        values.push(Box::new(keywords.len() as i64));
+        conditions.push(format!(
+            "(SELECT COUNT(*) FROM movie_keywords k WHERE k.movie_id = m.id \
+             AND lower(k.keyword) IN rarray(?{})) = ?{}",
+            values.len() - 1,
+            values.len()
+        ));
+    }
+
+    if let Some(keywords_exclude) = &criteria.keywords_exclude {
+        values.push(Box::new(lowercase_array(keywords_exclude)));
+        conditions.push(format!(
+            "NOT EXISTS (SELECT 1 FROM movie_keywords k WHERE k.movie_id = m.id \
+             AND lower(k.keyword) IN rarray(?{}))",
+            values.len()
+        ));
+    }
+
+    // TMDB uses 0 for an unknown budget or revenue, which never matches a range
+    if criteria.budget_min.is_some() || criteria.budget_max.is_some() {
+        conditions.push(String::from("m.budget > 0"));
+        push_range(
+            "m.budget",
+            criteria.budget_min.map(clamp_to_i64),
+            criteria.budget_max.map(clamp_to_i64),
+            &mut conditions,
+            &mut values,
+        );
+    }
+
+    if criteria.revenue_min.is_some() || criteria.revenue_max.is_some() {
+        conditions.push(String::from("m.revenue > 0"));
+        push_range(
+            "m.revenue",
+            criteria.revenue_min.map(clamp_to_i64),
+            criteria.revenue_max.map(clamp_to_i64),
+            &mut conditions,
+            &mut values,
+        );
+    }
+
+    push_range(
+        "m.vote_count",
+        criteria.vote_count_min,
+        None,
+        &mut conditions,
+        &mut values,
+    );
+    push_range(
+        "m.popularity",
+        criteria.popularity_min,
+        criteria.popularity_max,
+        &mut conditions,
+        &mut values,
+    );
+
+    if conditions.is_empty() {
+        (String::new(), values)
+    } else {
+        (format!("WHERE {}", conditions.join(" AND ")), values)
+    }
+}
+
+fn push_range<T: ToSql + 'static>(
+    column: &str,
+    min: Option<T>,
+    max: Option<T>,
+    conditions: &mut Vec<String>,
+    values: &mut Vec<Box<dyn ToSql>>,
+) {
+    if let Some(min) = min {
+        values.push(Box::new(min));
+        conditions.push(format!("{} >= ?{}", column, values.len()));
+    }
+
+    if let Some(max) = max {
+        values.push(Box::new(max));
+        conditions.push(format!("{} <= ?{}", column, values.len()));
+    }
+}
+
+// SQLite integers are signed
+fn clamp_to_i64(value: u64) -> i64 {
+    i64::try_from(value).unwrap_or(i64::MAX)
+}
+
+fn lowercase_array(values: &[String]) -> Rc<Vec<Value>> {
+    Rc::new(
+        values
+            .iter()
+            .map(|x| Value::from(x.to_lowercase()))
+            .collect(),
+    )
+}
+
+fn text_array(values: &[String]) -> Rc<Vec<Value>> {
+    Rc::new(values.iter().map(|x| Value::from(x.clone())).collect())
+}
+
+fn movie_from_row(row: &Row) -> Result<TopRatedMovie, rusqlite::Error> {
+    let summaries: Option<String> = row.get(16)?;
+
+    Ok(TopRatedMovie {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        backdrop_path: row.get(2)?,
+        poster_path: row.get(3)?,
+        release_date: row.get(4)?,
+        vote_average: row.get(5)?,
+        vote_count: row.get(6)?,
+        popularity: row.get(7)?,
+        overview: row.get(8)?,
+        imdb_id: row.get(9)?,
+        budget: row.get(10)?,
+        homepage: row.get(11)?,
+        revenue: row.get(12)?,
+        runtime: row.get(13)?,
+        tagline: row.get(14)?,
+        genres: Vec::new(),
+        cast: None,
+        keywords: None,
+        mpaa: row.get(15)?,
+        summaries: summaries.and_then(|x| serde_json::from_str(&x).ok()),
+        synopsis: row.get(17)?,
+        imdb_score: row.get(18)?,
+    })
+}
+
+fn load_related(
+    connection: &Connection,
+    movie: &mut TopRatedMovie,
+) -> Result<(), Box<dyn std::error::Error>> {
+    movie.genres = connection
+        .prepare_cached("SELECT genre FROM movie_genres WHERE movie_id = ?1 ORDER BY rowid")?
+        .query_map(params![movie.id], |row| row.get(0))?
+        .collect::<Result<Vec<String>, rusqlite::Error>>()?;
+
+    let keywords = connection
+        .prepare_cached("SELECT keyword FROM movie_keywords WHERE movie_id = ?1 ORDER BY rowid")?
+        .query_map(params![movie.id], |row| row.get(0))?
+        .collect::<Result<Vec<String>, rusqlite::Error>>()?;
+    movie.keywords = if keywords.is_empty() {
+        None
+    } else {
+        Some(keywords)
+    };
+
+    let cast = connection
+        .prepare_cached(
+            "SELECT name, character, profile_path FROM movie_cast \
+             WHERE movie_id = ?1 ORDER BY position",
+        )?
+        .query_map(params![movie.id], |row| {
+            Ok(Cast {
+                name: row.get(0)?,
+                character: row.get(1)?,
+                profile_path: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<Cast>, rusqlite::Error>>()?;
+    movie.cast = if cast.is_empty() { None } else { Some(cast) };
+
+    Ok(())
+}
+
+fn embedding_to_blob(embedding: &[f32]) -> Vec<u8> {
+    embedding.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn blob_to_embedding(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|x| f32::from_le_bytes([x[0], x[1], x[2], x[3]]))
+        .collect()
+}
@@ -0,0 +1,151 @@
+pub mod movie_tools;
+
+use crate::model::cache::Cache;
+use crate::model::chat_completion_request::{Message, RequestTool, ToolFunction};
+use crate::model::chat_completion_response::ToolCall;
+use crate::model::config::Config;
+use crate::store::movie_repository::MovieRepository;
+use async_trait::async_trait;
+use futures::future::join_all;
+use log::{debug, warn};
+use movie_tools::{FindSimilarMoviesTool, GetMovieDetailsTool, ListGenresTool, SemanticSearchTool};
+use std::sync::Mutex;
+
+// What a tool handler can reach while answering a call
+pub struct ToolContext<'a> {
+    pub config: &'a Config,
+    pub cache: &'a Mutex<Cache>,
+    pub movie_repository: &'a dyn MovieRepository,
+}
+
+/**
+ * A function the chat model can call.
+ *
+ * The schema describes the arguments the model sends, the handler turns them into a JSON result that's
+ * sent back to the model as a tool message.
+ */
+#[async_trait(?Send)]
+pub trait MovieTool: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn description(&self) -> &'static str;
+
+    // JSON schema of the arguments object
+    fn parameters(&self) -> serde_json::Value;
+
+    async fn call(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext<'_>,
+    ) -> Result<serde_json::Value, Box<dyn std::error::Error>>;
+}
+
+pub struct ToolRegistry {
+    tools: Vec<Box<dyn MovieTool>>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        ToolRegistry { tools: Vec::new() }
+    }
+
+    // The lookup tools available to movie_chat
+    pub fn with_movie_tools() -> Self {
+        ToolRegistry::new()
+            .register(Box::new(GetMovieDetailsTool))
+            .register(Box::new(FindSimilarMoviesTool))
+            .register(Box::new(SemanticSearchTool))
+            .register(Box::new(ListGenresTool))
+    }
+
+    pub fn register(mut self, tool: Box<dyn MovieTool>) -> Self {
+        self.tools.push(tool);
+        self
+    }
+
+    // Tool definitions to send with a chat completion request
+    pub fn definitions(&self) -> Vec<RequestTool> {
+        self.tools
+            .iter()
+            .map(|tool| {
+                RequestTool::builder()
+                    .function(
+                        ToolFunction::builder()
+                            .name(tool.name().to_string())
+                            .description(tool.description().to_string())
+                            .parameters(tool.parameters())
+                            .build(),
+                    )
+                    .build()
+            })
+            .collect()
+    }
+
+    /**
+     * Runs the named tool with the model's raw JSON arguments.
+     *
+     * @param name - The function name from the tool call.
+     * @param arguments - The arguments string from the tool call, `{}` when the model sent none.
+     */
+    pub async fn call(
+        &self,
+        name: &str,
+        arguments: &str,
+        context: &ToolContext<'_>,
+    ) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        let tool = self
+            .tools
+            .iter()
+            .find(|x| x.name() == name)
+            .ok_or_else(|| format!("Unknown tool: {}", name))?;
+
+        let arguments: serde_json::Value = if arguments.trim().is_empty() {
+            serde_json::json!({})
+        } else {
+            serde_json::from_str(arguments)?
+        };
+        debug!("Calling tool {} with {}", name, arguments);
+
+        tool.call(arguments, context).await
+    }
+
+    /**
+     * Runs the tool calls concurrently and returns one "tool" message per call, tied to its `tool_call_id`.
+     *
+     * A failing call doesn't fail the others; its error is sent to the model as the result instead.
+     */
+    pub async fn call_all(
+        &self,
+        tool_calls: &[&ToolCall],
+        context: &ToolContext<'_>,
+    ) -> Vec<Message> {
+        join_all(tool_calls.iter().map(|tool_call| async move {
+            let name = tool_call.function.name.as_deref().unwrap_or_default();
+            let arguments = tool_call.function.arguments.as_deref().unwrap_or_default();
+
+            let result = match self.call(name, arguments, context).await {
+                Ok(result) => result,
+                Err(e) => {
+                    warn!("Tool call {} to {} failed: {}", tool_call.id, name, e);
+                    tool_error(&e.to_string())
+                }
+            };
+            debug!("Tool {} returned {}", name, result);
+
+            tool_message(&tool_call.id, &result)
+        }))
+        .await
+    }
+}
+
+pub fn tool_error(message: &str) -> serde_json::Value {
+    serde_json::json!({ "error": message })
+}
+
+pub fn tool_message(tool_call_id: &str, result: &serde_json::Value) -> Message {
+    Message::builder()
+        .role(String::from("tool"))
+        .tool_call_id(tool_call_id.to_string())
+        .content(result.to_string())
+        .build()
+}
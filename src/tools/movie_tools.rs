@@ -0,0 +1,260 @@
+use crate::model::movies::movie::TopRatedMovie;
+use crate::tools::{MovieTool, ToolContext};
+use crate::util::embedding_helper::create_embedding;
+use crate::util::movie_helper::{load_movie_embeddings, rank_by_embedding};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use utoipa::{PartialSchema, ToSchema};
+
+const DEFAULT_TOOL_LIMIT: usize = 5;
+const MAX_TOOL_LIMIT: usize = 20;
+
+#[derive(Deserialize, ToSchema)]
+struct MovieIdArguments {
+    /// TMDB ID of the movie
+    movie_id: i32,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct SimilarMoviesArguments {
+    /// TMDB ID of the movie to compare against
+    movie_id: i32,
+    /// How many movies to return, 5 when left out
+    #[schema(minimum = 1, maximum = 20)]
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct SemanticSearchArguments {
+    /// A description of the movie the user is after, e.g. "a heist that goes wrong"
+    query: String,
+    /// How many movies to return, 5 when left out
+    #[schema(minimum = 1, maximum = 20)]
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct NoArguments {}
+
+pub struct GetMovieDetailsTool;
+
+#[async_trait(?Send)]
+impl MovieTool for GetMovieDetailsTool {
+    fn name(&self) -> &'static str {
+        "get_movie_details"
+    }
+
+    fn description(&self) -> &'static str {
+        "Looks up the full details of a movie, including its overview, runtime, rating and top-billed cast."
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        schema_of(MovieIdArguments::schema())
+    }
+
+    async fn call(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext<'_>,
+    ) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        let arguments: MovieIdArguments = serde_json::from_value(arguments)?;
+
+        match context
+            .movie_repository
+            .get_movie(arguments.movie_id)
+            .await?
+        {
+            Some(movie) => Ok(movie_details(&movie)),
+            None => Err(format!("No movie with ID {}", arguments.movie_id).into()),
+        }
+    }
+}
+
+pub struct FindSimilarMoviesTool;
+
+#[async_trait(?Send)]
+impl MovieTool for FindSimilarMoviesTool {
+    fn name(&self) -> &'static str {
+        "find_similar_movies"
+    }
+
+    fn description(&self) -> &'static str {
+        "Finds the movies most similar to a given movie, by comparing their embeddings."
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        schema_of(SimilarMoviesArguments::schema())
+    }
+
+    async fn call(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext<'_>,
+    ) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        let arguments: SimilarMoviesArguments = serde_json::from_value(arguments)?;
+
+        if !load_movie_embeddings(context.cache) {
+            return Err("Movie embeddings aren't available".into());
+        }
+
+        let cosine_similarities = {
+            let cache_lock = context.cache.lock().unwrap();
+            let movie_embeddings = cache_lock.movie_embeddings.lock().unwrap();
+
+            let embedding = movie_embeddings
+                .iter()
+                .find(|x| x.movie_id == arguments.movie_id)
+                .and_then(|x| x.embeddings.as_ref())
+                .and_then(|x| x.data.first())
+                .map(|x| x.embedding.clone())
+                .ok_or_else(|| format!("No embedding for movie {}", arguments.movie_id))?;
+
+            rank_by_embedding(&embedding, &movie_embeddings)
+        };
+
+        let movie_ids: Vec<i32> = cosine_similarities
+            .iter()
+            .filter(|x| x.movie_id != arguments.movie_id)
+            .take(clamp_limit(arguments.limit))
+            .map(|x| x.movie_id)
+            .collect();
+        let movies = context.movie_repository.get_movies(&movie_ids).await?;
+
+        Ok(serde_json::json!(movies
+            .iter()
+            .map(movie_summary)
+            .collect::<Vec<serde_json::Value>>()))
+    }
+}
+
+pub struct SemanticSearchTool;
+
+#[async_trait(?Send)]
+impl MovieTool for SemanticSearchTool {
+    fn name(&self) -> &'static str {
+        "semantic_search"
+    }
+
+    fn description(&self) -> &'static str {
+        "Finds movies whose plot, themes and mood match a free-text description."
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        schema_of(SemanticSearchArguments::schema())
+    }
+
+    async fn call(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext<'_>,
+    ) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        let arguments: SemanticSearchArguments = serde_json::from_value(arguments)?;
+
+        if !load_movie_embeddings(context.cache) {
+            return Err("Movie embeddings aren't available".into());
+        }
+
+        let query_embedding = create_embedding(&arguments.query, context.config).await?;
+
+        let movie_ids: Vec<i32> = {
+            let cache_lock = context.cache.lock().unwrap();
+            let movie_embeddings = cache_lock.movie_embeddings.lock().unwrap();
+
+            rank_by_embedding(&query_embedding, &movie_embeddings)
+                .iter()
+                .take(clamp_limit(arguments.limit))
+                .map(|x| x.movie_id)
+                .collect()
+        };
+        let movies = context.movie_repository.get_movies(&movie_ids).await?;
+
+        Ok(serde_json::json!(movies
+            .iter()
+            .map(movie_summary)
+            .collect::<Vec<serde_json::Value>>()))
+    }
+}
+
+pub struct ListGenresTool;
+
+#[async_trait(?Send)]
+impl MovieTool for ListGenresTool {
+    fn name(&self) -> &'static str {
+        "list_genres"
+    }
+
+    fn description(&self) -> &'static str {
+        "Lists the genres in the catalogue with how many movies each one has."
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        schema_of(NoArguments::schema())
+    }
+
+    async fn call(
+        &self,
+        _arguments: serde_json::Value,
+        context: &ToolContext<'_>,
+    ) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        let mut genre_counts: BTreeMap<String, usize> = BTreeMap::new();
+        for movie in context.movie_repository.list_movies().await? {
+            for genre in movie.genres {
+                *genre_counts.entry(genre).or_insert(0) += 1;
+            }
+        }
+
+        Ok(serde_json::json!(genre_counts))
+    }
+}
+
+fn schema_of(schema: impl serde::Serialize) -> serde_json::Value {
+    let mut schema = serde_json::to_value(schema).unwrap_or_default();
+
+    // Function parameters must always describe an object, even when it has no fields
+    if schema.get("properties").is_none() {
+        schema["properties"] = serde_json::json!({});
+    }
+
+    schema
+}
+
+fn clamp_limit(limit: Option<usize>) -> usize {
+    limit.unwrap_or(DEFAULT_TOOL_LIMIT).clamp(1, MAX_TOOL_LIMIT)
+}
+
+// Just enough for the model to mention a movie and pick one to look up
+fn movie_summary(movie: &TopRatedMovie) -> serde_json::Value {
+    serde_json::json!({
+        "id": movie.id,
+        "title": movie.title,
+        "release_date": movie.release_date,
+        "genres": movie.genres,
+        "mpaa": movie.mpaa,
+        "imdb_score": movie.imdb_score,
+    })
+}
+
+fn movie_details(movie: &TopRatedMovie) -> serde_json::Value {
+    let cast: Vec<String> = movie
+        .cast
+        .iter()
+        .flatten()
+        .take(5)
+        .map(|x| format!("{} as {}", x.name, x.character))
+        .collect();
+
+    serde_json::json!({
+        "id": movie.id,
+        "title": movie.title,
+        "release_date": movie.release_date,
+        "genres": movie.genres,
+        "mpaa": movie.mpaa,
+        "imdb_score": movie.imdb_score,
+        "runtime": movie.runtime,
+        "tagline": movie.tagline,
+        "overview": movie.overview,
+        "keywords": movie.keywords,
+        "cast": cast,
+    })
+}
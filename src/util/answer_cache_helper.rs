@@ -0,0 +1,80 @@
+use crate::model::answered_question::AnsweredQuestion;
+use crate::util::vector_math_helper::VectorMathHelper;
+use log::debug;
+use std::collections::{HashMap, VecDeque};
+
+pub fn normalize_question(question: &str) -> String {
+    question
+        .trim()
+        .trim_end_matches(['?', '.', '!'])
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+pub fn find_exact_answer(
+    movie_id: i32,
+    question: &str,
+    answered_questions: &HashMap<i32, VecDeque<AnsweredQuestion>>,
+) -> Option<String> {
+    let normalized_question = normalize_question(question);
+
+    answered_questions
+        .get(&movie_id)?
+        .iter()
+        .find(|x| normalize_question(&x.question) == normalized_question)
+        .map(|x| x.answer.clone())
+}
+
+pub fn find_similar_answer(
+    movie_id: i32,
+    question_embedding: &[f32],
+    answered_questions: &HashMap<i32, VecDeque<AnsweredQuestion>>,
+    similarity_threshold: f32,
+) -> Option<String> {
+    let mut best_match: Option<(f32, &AnsweredQuestion)> = None;
+
+    for answered_question in answered_questions
+        .get(&movie_id)?
+        .iter()
+        .filter(|x| x.embedding.len() == question_embedding.len())
+    {
+        let similarity =
+            VectorMathHelper::cosine_similarity(question_embedding, &answered_question.embedding);
+        debug!(
+            "Similarity to \"{}\": {}",
+            answered_question.question, similarity
+        );
+
+        if similarity >= similarity_threshold
+            && best_match.is_none_or(|(best, _)| similarity > best)
+        {
+            best_match = Some((similarity, answered_question));
+        }
+    }
+
+    best_match.map(|(_, answered_question)| answered_question.answer.clone())
+}
+
+/**
+ * Stores an answer under its movie, dropping that movie's oldest answers past the cap.
+ *
+ * @param movie_id - The movie the question was about.
+ * @param answered_question - The question, its embedding and the answer.
+ * @param answered_questions - The cached answers, keyed by movie ID.
+ * @param max_per_movie - How many answers to keep for each movie.
+ */
+pub fn remember_answer(
+    movie_id: i32,
+    answered_question: AnsweredQuestion,
+    answered_questions: &mut HashMap<i32, VecDeque<AnsweredQuestion>>,
+    max_per_movie: usize,
+) {
+    let movie_answers = answered_questions.entry(movie_id).or_default();
+    movie_answers.push_back(answered_question);
+
+    while movie_answers.len() > max_per_movie {
+        movie_answers.pop_front();
+    }
+}
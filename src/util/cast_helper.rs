@@ -0,0 +1,74 @@
+use crate::model::cache::Cache;
+use crate::model::movies::movie::Cast;
+use log::debug;
+use spinners::{Spinner, Spinners};
+use std::collections::HashSet;
+use std::{fs, sync::Mutex};
+
+// Cast lives under credits.cast in the TMDB detail files
+pub fn take_cast(movie: &mut serde_json::Value) -> Result<Vec<Cast>, serde_json::Error> {
+    match movie.pointer_mut("/credits/cast") {
+        Some(cast) => serde_json::from_value(cast.take()),
+        None => Ok(Vec::new()),
+    }
+}
+
+/**
+ * Builds the cast index from the detail files in src/data/movies, unless it's already loaded.
+ *
+ * @param cache - The shared cache holding the index.
+ * @returns False when there are no detail files to index.
+ */
+pub fn load_cast_index(cache: &Mutex<Cache>) -> bool {
+    let movies_path = std::env::current_dir().unwrap().join("src/data/movies");
+
+    let cache_lock = cache.lock().unwrap();
+    let mut cast_index_lock = cache_lock.cast_index.lock().unwrap();
+
+    if !cast_index_lock.is_empty() {
+        return true;
+    }
+
+    let entries = match fs::read_dir(&movies_path) {
+        Ok(entries) => entries,
+        Err(_) => return false,
+    };
+
+    let mut sp = Spinner::new(Spinners::Dots9, "\t\tIndexing cast names...".into());
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let movie_id: i32 = match path.file_stem().and_then(|x| x.to_str()).map(str::parse) {
+            Some(Ok(movie_id)) => movie_id,
+            _ => continue,
+        };
+
+        let cast = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|x| serde_json::from_str(&x).map_err(|e| e.to_string()))
+            .and_then(|mut x| take_cast(&mut x).map_err(|e| e.to_string()));
+        match cast {
+            Ok(cast) => {
+                for cast_member in cast {
+                    cast_index_lock.insert(&cast_member.name, movie_id);
+                }
+            }
+            Err(e) => debug!("Error reading cast from {:?}: {}", path, e),
+        }
+    }
+    sp.stop();
+    debug!("Indexed {} cast members", cast_index_lock.len());
+
+    true
+}
+
+// The catalogue movies every one of the actors appears in
+pub fn find_cast_movie_ids(cache: &Mutex<Cache>, names: &[String]) -> Option<HashSet<i32>> {
+    if !load_cast_index(cache) {
+        return None;
+    }
+
+    let cache_lock = cache.lock().unwrap();
+    let cast_index_lock = cache_lock.cast_index.lock().unwrap();
+
+    Some(cast_index_lock.movie_ids_with_all(names))
+}
@@ -0,0 +1,33 @@
+use crate::model::chat_completion_request::ChatCompletionRequest;
+use crate::model::chat_completion_response::ChatCompletionResponse;
+use crate::model::config::Config;
+use log::debug;
+use serde_json::{from_str, to_string};
+
+pub async fn send_chat_completion(
+    oai_request: &ChatCompletionRequest,
+    config: &Config,
+) -> Result<ChatCompletionResponse, Box<dyn std::error::Error>> {
+    let client = reqwest::Client::new();
+
+    let body = to_string(oai_request)?;
+    debug!("Body: {}", body);
+
+    let prompt_response = client
+        .post(format!(
+            "{}openai/deployments/{}/chat/completions?api-version={}",
+            config.open_ai.url, config.open_ai.model, config.open_ai.api_version
+        ))
+        .header("Content-Type", "application/json")
+        .header("api-key", config.open_ai.key.clone())
+        .body(body)
+        .send()
+        .await?;
+
+    let response_body = prompt_response.text().await?;
+    debug!("Response body: {}", response_body);
+
+    let json: ChatCompletionResponse = from_str(&response_body)?;
+
+    Ok(json)
+}
@@ -0,0 +1,220 @@
+use crate::model::chat_completion_request::{ChatCompletionRequest, Message};
+use crate::model::config::{Config, ContextStrategy};
+use crate::model::conversation::{Conversation, HistorySummary};
+use crate::util::chat_completion_helper::send_chat_completion;
+use log::{debug, warn};
+use tiktoken_rs::cl100k_base_singleton;
+
+// Every reply is primed with <|start|>assistant<|message|>
+const REPLY_PRIMING_TOKENS: usize = 3;
+// Share of the budget left to recent messages when the summary is rewritten
+const SUMMARIZED_BUDGET_PERCENT: usize = 75;
+
+pub fn estimate_text_tokens(text: &str) -> usize {
+    cl100k_base_singleton()
+        .lock()
+        .encode_with_special_tokens(text)
+        .len()
+}
+
+pub fn estimate_messages_tokens(messages: &[Message]) -> usize {
+    messages.iter().map(|x| x.estimate_tokens()).sum()
+}
+
+/**
+ * Trims (or summarizes) the oldest chat history so the request stays under the configured context window.
+ *
+ * With the summarize strategy the summary is kept on the conversation and only rewritten when more messages fall
+ * out of the window than it already covers.
+ *
+ * @param conversation - The conversation, its messages oldest first and without the system prompt.
+ * @param fixed_tokens - Tokens already spent on the system prompt and tool definitions.
+ * @returns The messages to send after the system prompt. The newest message is always kept.
+ */
+pub async fn fit_to_context_window(
+    conversation: &mut Conversation,
+    fixed_tokens: usize,
+    config: &Config,
+) -> Vec<Message> {
+    let context_config = &config.context_window;
+    let budget = context_config
+        .max_tokens
+        .saturating_sub(context_config.reserved_completion_tokens)
+        .saturating_sub(fixed_tokens)
+        .saturating_sub(REPLY_PRIMING_TOKENS);
+    let history = &conversation.messages;
+
+    let history_tokens = estimate_messages_tokens(history);
+    debug!(
+        "Chat history is {} tokens with a budget of {}",
+        history_tokens, budget
+    );
+
+    if history_tokens <= budget {
+        return history.clone();
+    }
+
+    if matches!(context_config.strategy, ContextStrategy::Summarize) {
+        match summarized_history(conversation, budget, config).await {
+            Ok(messages) => return messages,
+            Err(e) => warn!("Error summarizing chat history, trimming instead: {}", e),
+        }
+    }
+
+    let history = &conversation.messages;
+    let split_index = recent_messages_start(history, budget);
+    debug!(
+        "Dropping {} older messages to fit the context window",
+        split_index
+    );
+
+    history[split_index..].to_vec()
+}
+
+// The conversation's summary followed by the messages it doesn't cover, summarizing more of them when they don't fit
+async fn summarized_history(
+    conversation: &mut Conversation,
+    budget: usize,
+    config: &Config,
+) -> Result<Vec<Message>, Box<dyn std::error::Error>> {
+    if let Some(history_summary) = &conversation.history_summary {
+        let summary_message = summary_message(&history_summary.content);
+        let recent_budget = budget.saturating_sub(summary_message.estimate_tokens());
+        let split_index = recent_messages_start(&conversation.messages, recent_budget);
+
+        if split_index <= history_summary.message_count {
+            debug!(
+                "Reusing the summary of {} older messages",
+                history_summary.message_count
+            );
+            let mut messages = vec![summary_message];
+            messages.extend_from_slice(&conversation.messages[history_summary.message_count..]);
+            return Ok(messages);
+        }
+    }
+
+    // Summarize past what's needed right now so the next few turns fit without another summary
+    let previous = conversation.history_summary.take();
+    let previous_tokens = previous
+        .as_ref()
+        .map_or(0, |x| summary_message(&x.content).estimate_tokens());
+    let recent_budget =
+        (budget * SUMMARIZED_BUDGET_PERCENT / 100).min(budget.saturating_sub(previous_tokens));
+    let summarized_from = previous.as_ref().map_or(0, |x| x.message_count);
+    let split_index =
+        recent_messages_start(&conversation.messages, recent_budget).max(summarized_from);
+    debug!(
+        "Summarizing {} more older messages to fit the context window",
+        split_index - summarized_from
+    );
+
+    let summary = summarize_messages(
+        previous.as_ref().map(|x| x.content.as_str()),
+        &conversation.messages[summarized_from..split_index],
+        config,
+    )
+    .await;
+    let summary = match summary {
+        Ok(summary) => summary,
+        Err(e) => {
+            conversation.history_summary = previous;
+            return Err(e);
+        }
+    };
+
+    let summary_message = summary_message(&summary);
+    conversation.history_summary = Some(HistorySummary {
+        content: summary,
+        message_count: split_index,
+    });
+
+    // Make room for the summary itself
+    let recent = &conversation.messages[split_index..];
+    let recent_budget = budget.saturating_sub(summary_message.estimate_tokens());
+    let recent_start = recent_messages_start(recent, recent_budget);
+
+    let mut messages = vec![summary_message];
+    messages.extend_from_slice(&recent[recent_start..]);
+    Ok(messages)
+}
+
+fn summary_message(summary: &str) -> Message {
+    Message::builder()
+        .role(String::from("system"))
+        .content(format!("Summary of the earlier conversation: {}", summary))
+        .build()
+}
+
+// Index of the oldest message in the longest run of recent messages that fits in the budget
+fn recent_messages_start(messages: &[Message], budget: usize) -> usize {
+    if messages.is_empty() {
+        return 0;
+    }
+
+    let mut used_tokens = 0;
+    let mut start = messages.len();
+
+    for (index, message) in messages.iter().enumerate().rev() {
+        let message_tokens = message.estimate_tokens();
+        // The newest message is always kept, even when it alone is over budget
+        if used_tokens + message_tokens > budget && start < messages.len() {
+            break;
+        }
+        used_tokens += message_tokens;
+        start = index;
+    }
+
+    // Tool results can't be sent without the assistant message that requested them
+    while start < messages.len() - 1 && messages[start].role == "tool" {
+        start += 1;
+    }
+
+    start
+}
+
+async fn summarize_messages(
+    previous_summary: Option<&str>,
+    messages: &[Message],
+    config: &Config,
+) -> Result<String, Box<dyn std::error::Error>> {
+    // Extend the earlier summary rather than summarizing the whole conversation again
+    let transcript: Vec<String> = previous_summary
+        .map(|x| format!("summary of the conversation so far: {}", x))
+        .into_iter()
+        .chain(messages.iter().filter_map(|x| {
+            x.content
+                .as_ref()
+                .map(|content| format!("{}: {}", x.role, content))
+        }))
+        .collect();
+
+    let system_message = Message::builder()
+        .role(String::from("system"))
+        .content(String::from(
+            "Summarize the following conversation between a user and a movie recommendation assistant in a few sentences. Keep every preference, movie and requirement the user mentioned.",
+        ))
+        .build();
+
+    let user_message = Message::builder()
+        .role(String::from("user"))
+        .content(transcript.join("\n"))
+        .build();
+
+    let oai_request = ChatCompletionRequest::builder()
+        .model(config.open_ai.model.clone())
+        .sampling(&config.sampling.summary)
+        .message(system_message)
+        .message(user_message)
+        .build();
+
+    let json = send_chat_completion(&oai_request, config).await?;
+    let summary = json
+        .choices
+        .iter()
+        .find_map(|x| x.message.content.clone())
+        .filter(|x| !x.trim().is_empty())
+        .ok_or("The model didn't reply with a summary")?;
+    debug!("Summary: {}", summary);
+
+    Ok(summary)
+}
@@ -0,0 +1,469 @@
+use crate::model::movies::movie_criteria::{CriteriaError, MovieCriteria};
+use crate::util::date_helper::{format_date, parse_date_bound, today, years_before, DateBound};
+use crate::util::tool_helper::criteria_fields;
+use chrono::NaiveDate;
+use log::debug;
+
+pub const GENRES: &[&str] = &[
+    "Action",
+    "Adventure",
+    "Animation",
+    "Comedy",
+    "Crime",
+    "Documentary",
+    "Drama",
+    "Family",
+    "Fantasy",
+    "History",
+    "Horror",
+    "Music",
+    "Mystery",
+    "Romance",
+    "Science Fiction",
+    "Thriller",
+    "War",
+    "Western",
+    "TV Movie",
+];
+
+pub const MPAA_RATINGS: &[&str] = &["G", "PG", "PG-13", "R", "NC-17", "NR"];
+
+// Spellings the model tends to use for genres in the list
+const GENRE_ALIASES: &[(&str, &str)] = &[
+    ("sci-fi", "Science Fiction"),
+    ("scifi", "Science Fiction"),
+    ("science-fiction", "Science Fiction"),
+    ("animated", "Animation"),
+    ("historical", "History"),
+    ("musical", "Music"),
+    ("romantic", "Romance"),
+    ("romcom", "Romance"),
+    ("tv", "TV Movie"),
+];
+
+/**
+ * Parses and validates criteria produced by the model.
+ *
+ * Unknown fields and values of the wrong type are reported rather than silently dropped, so the model can be
+ * asked to fix them.
+ *
+ * @param text - The raw JSON the model returned.
+ */
+pub fn parse_criteria(text: &str) -> Result<MovieCriteria, Vec<CriteriaError>> {
+    let value: serde_json::Value = serde_json::from_str(text)
+        .map_err(|e| vec![CriteriaError::new("$", format!("is not valid JSON: {}", e))])?;
+
+    let object = match value.as_object() {
+        Some(object) => object,
+        None => {
+            return Err(vec![CriteriaError::new(
+                "$",
+                String::from("must be a JSON object"),
+            )])
+        }
+    };
+
+    let criteria_fields = criteria_fields();
+    let unknown_fields: Vec<CriteriaError> = object
+        .keys()
+        .filter(|key| !criteria_fields.contains(key))
+        .map(|key| {
+            CriteriaError::new(
+                key,
+                format!(
+                    "is not a known field, use one of: {}",
+                    criteria_fields.join(", ")
+                ),
+            )
+        })
+        .collect();
+    if !unknown_fields.is_empty() {
+        return Err(unknown_fields);
+    }
+
+    let criteria: MovieCriteria = serde_json::from_value(value).map_err(|e| {
+        vec![CriteriaError::new(
+            "$",
+            format!("has the wrong shape: {}", e),
+        )]
+    })?;
+
+    validate_criteria(criteria)
+}
+
+/**
+ * Normalizes the criteria and checks every field, collecting all of the problems.
+ *
+ * Genres and MPAA ratings are mapped onto their canonical spelling, blank strings become `None`.
+ *
+ * @param criteria - The criteria to check.
+ */
+pub fn validate_criteria(criteria: MovieCriteria) -> Result<MovieCriteria, Vec<CriteriaError>> {
+    let mut errors: Vec<CriteriaError> = Vec::new();
+
+    let genre = non_empty(criteria.genre)
+        .map(|genre| genre.split(',').map(|g| g.to_string()).collect())
+        .and_then(|genres| validate_genres("genre", genres, &mut errors))
+        .map(|genres| genres.join(","));
+    let genres_any = criteria
+        .genres_any
+        .and_then(|genres| validate_genres("genres_any", genres, &mut errors));
+    let genres_all = criteria
+        .genres_all
+        .and_then(|genres| validate_genres("genres_all", genres, &mut errors));
+    let genres_exclude = criteria
+        .genres_exclude
+        .and_then(|genres| validate_genres("genres_exclude", genres, &mut errors));
+    check_disjoint(
+        ("genres_all", &genres_all),
+        ("genres_exclude", &genres_exclude),
+        &mut errors,
+    );
+    check_disjoint(
+        ("genres_any", &genres_any),
+        ("genres_exclude", &genres_exclude),
+        &mut errors,
+    );
+
+    let mpaa = non_empty(criteria.mpaa)
+        .and_then(|mpaa| validate_ratings("mpaa", vec![mpaa], &mut errors))
+        .and_then(|ratings| ratings.into_iter().next());
+    let mpaa_any = criteria
+        .mpaa_any
+        .and_then(|ratings| validate_ratings("mpaa_any", ratings, &mut errors));
+    let mpaa_exclude = criteria
+        .mpaa_exclude
+        .and_then(|ratings| validate_ratings("mpaa_exclude", ratings, &mut errors));
+    check_disjoint(
+        ("mpaa_any", &mpaa_any),
+        ("mpaa_exclude", &mpaa_exclude),
+        &mut errors,
+    );
+
+    let cast = criteria
+        .cast
+        .and_then(|cast| validate_cast(cast, &mut errors));
+
+    let today = today();
+    let mut release_date_min = validate_date(
+        "release_date_min",
+        criteria.release_date_min,
+        DateBound::Start,
+        today,
+        &mut errors,
+    );
+    let release_date_max = validate_date(
+        "release_date_max",
+        criteria.release_date_max,
+        DateBound::End,
+        today,
+        &mut errors,
+    );
+
+    // "The last five years" becomes a minimum date, keeping an explicit minimum if it's later
+    if let Some(years) = criteria.released_within_years {
+        match years_before(today, years).filter(|_| years > 0) {
+            Some(date) => {
+                release_date_min = Some(release_date_min.map_or(date, |x| x.max(date)));
+            }
+            None => errors.push(CriteriaError::new(
+                "released_within_years",
+                format!("{} is not a positive number of years", years),
+            )),
+        }
+    }
+    if let (Some(min), Some(max)) = (&release_date_min, &release_date_max) {
+        if min > max {
+            errors.push(CriteriaError::new(
+                "release_date_min",
+                format!("{} is after release_date_max {}", min, max),
+            ));
+        }
+    }
+
+    let score_min = validate_score("score_min", criteria.score_min, &mut errors);
+    let score_max = validate_score("score_max", criteria.score_max, &mut errors);
+    if let (Some(min), Some(max)) = (score_min, score_max) {
+        if min > max {
+            errors.push(CriteriaError::new(
+                "score_min",
+                format!("{} is greater than score_max {}", min, max),
+            ));
+        }
+    }
+
+    let keywords = criteria.keywords.and_then(validate_keywords);
+    let keywords_exclude = criteria.keywords_exclude.and_then(validate_keywords);
+    check_disjoint(
+        ("keywords", &keywords),
+        ("keywords_exclude", &keywords_exclude),
+        &mut errors,
+    );
+
+    check_range(
+        ("budget_min", criteria.budget_min),
+        ("budget_max", criteria.budget_max),
+        &mut errors,
+    );
+    check_range(
+        ("revenue_min", criteria.revenue_min),
+        ("revenue_max", criteria.revenue_max),
+        &mut errors,
+    );
+
+    let popularity_min =
+        validate_popularity("popularity_min", criteria.popularity_min, &mut errors);
+    let popularity_max =
+        validate_popularity("popularity_max", criteria.popularity_max, &mut errors);
+    check_range(
+        ("popularity_min", popularity_min),
+        ("popularity_max", popularity_max),
+        &mut errors,
+    );
+
+    if !errors.is_empty() {
+        debug!("Invalid criteria: {:?}", errors);
+        return Err(errors);
+    }
+
+    Ok(MovieCriteria {
+        search: non_empty(criteria.search),
+        genre,
+        genres_any,
+        genres_all,
+        genres_exclude,
+        mpaa,
+        mpaa_any,
+        mpaa_exclude,
+        cast,
+        release_date_min: release_date_min.map(format_date),
+        release_date_max: release_date_max.map(format_date),
+        released_within_years: None,
+        score_min,
+        score_max,
+        keywords,
+        keywords_exclude,
+        budget_min: criteria.budget_min,
+        budget_max: criteria.budget_max,
+        revenue_min: criteria.revenue_min,
+        revenue_max: criteria.revenue_max,
+        vote_count_min: criteria.vote_count_min,
+        popularity_min,
+        popularity_max,
+        natural_language: non_empty(criteria.natural_language),
+    })
+}
+
+pub fn normalize_genre(value: &str) -> Option<&'static str> {
+    let value = value.trim();
+
+    GENRES
+        .iter()
+        .find(|x| x.eq_ignore_ascii_case(value))
+        .or_else(|| {
+            GENRE_ALIASES
+                .iter()
+                .find(|(alias, _)| alias.eq_ignore_ascii_case(value))
+                .map(|(_, genre)| genre)
+        })
+        .copied()
+}
+
+pub fn normalize_mpaa(value: &str) -> Option<&'static str> {
+    // "pg13", "PG 13" and "Rated R" all mean the same thing
+    let compact: String = value
+        .to_uppercase()
+        .trim_start_matches("RATED")
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect();
+
+    match compact.as_str() {
+        "G" => Some("G"),
+        "PG" => Some("PG"),
+        "PG13" => Some("PG-13"),
+        "R" => Some("R"),
+        "NC17" => Some("NC-17"),
+        "NR" | "NOTRATED" | "UNRATED" => Some("NR"),
+        _ => None,
+    }
+}
+
+// Canonical genres without duplicates, None when the list ends up empty
+fn validate_genres(
+    field: &str,
+    values: Vec<String>,
+    errors: &mut Vec<CriteriaError>,
+) -> Option<Vec<String>> {
+    validate_values(field, values, normalize_genre, GENRES, errors)
+}
+
+fn validate_ratings(
+    field: &str,
+    values: Vec<String>,
+    errors: &mut Vec<CriteriaError>,
+) -> Option<Vec<String>> {
+    validate_values(field, values, normalize_mpaa, MPAA_RATINGS, errors)
+}
+
+fn validate_values(
+    field: &str,
+    values: Vec<String>,
+    normalize: fn(&str) -> Option<&'static str>,
+    allowed: &[&str],
+    errors: &mut Vec<CriteriaError>,
+) -> Option<Vec<String>> {
+    let mut normalized_values: Vec<String> = Vec::new();
+
+    for value in values.iter().map(|x| x.trim()).filter(|x| !x.is_empty()) {
+        match normalize(value) {
+            Some(normalized) => {
+                if !normalized_values.iter().any(|x| x == normalized) {
+                    normalized_values.push(normalized.to_string());
+                }
+            }
+            None => errors.push(CriteriaError::new(
+                field,
+                format!("'{}' is not one of: {}", value, allowed.join(", ")),
+            )),
+        }
+    }
+
+    if normalized_values.is_empty() {
+        None
+    } else {
+        Some(normalized_values)
+    }
+}
+
+// A value can't be both required and excluded
+fn check_disjoint(
+    (include_field, include): (&str, &Option<Vec<String>>),
+    (exclude_field, exclude): (&str, &Option<Vec<String>>),
+    errors: &mut Vec<CriteriaError>,
+) {
+    if let (Some(include), Some(exclude)) = (include, exclude) {
+        for value in include.iter().filter(|x| exclude.contains(x)) {
+            errors.push(CriteriaError::new(
+                exclude_field,
+                format!("'{}' is also in {}", value, include_field),
+            ));
+        }
+    }
+}
+
+// Trimmed names without duplicates, None when the list ends up empty
+fn validate_cast(values: Vec<String>, errors: &mut Vec<CriteriaError>) -> Option<Vec<String>> {
+    let mut names: Vec<String> = Vec::new();
+
+    for value in values.iter().map(|x| x.trim()).filter(|x| !x.is_empty()) {
+        if value.chars().filter(|c| c.is_alphanumeric()).count() < 2 {
+            errors.push(CriteriaError::new(
+                "cast",
+                format!("'{}' is too short to be an actor's name", value),
+            ));
+        } else if !names.iter().any(|x| x.eq_ignore_ascii_case(value)) {
+            names.push(value.to_string());
+        }
+    }
+
+    if names.is_empty() {
+        None
+    } else {
+        Some(names)
+    }
+}
+
+// TMDB keywords are lowercase phrases
+fn validate_keywords(values: Vec<String>) -> Option<Vec<String>> {
+    let mut keywords: Vec<String> = Vec::new();
+
+    for value in values.iter().map(|x| x.trim().to_lowercase()) {
+        if !value.is_empty() && !keywords.contains(&value) {
+            keywords.push(value);
+        }
+    }
+
+    if keywords.is_empty() {
+        None
+    } else {
+        Some(keywords)
+    }
+}
+
+fn check_range<T: PartialOrd + std::fmt::Display>(
+    (min_field, min): (&str, Option<T>),
+    (max_field, max): (&str, Option<T>),
+    errors: &mut Vec<CriteriaError>,
+) {
+    if let (Some(min), Some(max)) = (min, max) {
+        if min > max {
+            errors.push(CriteriaError::new(
+                min_field,
+                format!("{} is greater than {} {}", min, max_field, max),
+            ));
+        }
+    }
+}
+
+fn validate_popularity(
+    field: &str,
+    value: Option<f64>,
+    errors: &mut Vec<CriteriaError>,
+) -> Option<f64> {
+    let value = value?;
+
+    if !value.is_finite() || value < 0.0 {
+        errors.push(CriteriaError::new(
+            field,
+            format!("{} is not a positive number", value),
+        ));
+        return None;
+    }
+
+    Some(value)
+}
+
+fn validate_date(
+    field: &str,
+    value: Option<String>,
+    bound: DateBound,
+    today: NaiveDate,
+    errors: &mut Vec<CriteriaError>,
+) -> Option<NaiveDate> {
+    let value = non_empty(value)?;
+
+    match parse_date_bound(&value, bound, today) {
+        Some(date) => Some(date),
+        None => {
+            errors.push(CriteriaError::new(
+                field,
+                format!(
+                    "'{}' is not a date, use YYYY-MM-DD, a year like 1994 or a decade like 1990s",
+                    value
+                ),
+            ));
+            None
+        }
+    }
+}
+
+fn validate_score(field: &str, value: Option<f32>, errors: &mut Vec<CriteriaError>) -> Option<f32> {
+    let value = value?;
+
+    if !(0.0..=10.0).contains(&value) {
+        errors.push(CriteriaError::new(
+            field,
+            format!("{} is outside the 0-10 scale", value),
+        ));
+        return None;
+    }
+
+    Some(value)
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty())
+}
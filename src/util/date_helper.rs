@@ -0,0 +1,110 @@
+use chrono::{Datelike, Months, NaiveDate, Utc};
+
+// Which end of a year, month or decade a loose date stands for
+#[derive(Debug, Clone, Copy)]
+pub enum DateBound {
+    Start,
+    End,
+}
+
+pub fn today() -> NaiveDate {
+    Utc::now().date_naive()
+}
+
+/**
+ * Parses a release date filter into a calendar date.
+ *
+ * Besides YYYY-MM-DD this accepts a month (1994-06), a year (1994) and a decade (1990s, 90s or '90s). Those cover a
+ * range of days, so the bound picks the first or the last one. Two-digit decades are the most recent one that has
+ * started, "20s" is the 2020s and "30s" the 1930s.
+ *
+ * @param value - The date as given.
+ * @param bound - Whether this is the start or the end of the range being filtered.
+ * @param today - Used to place two-digit decades.
+ */
+pub fn parse_date_bound(value: &str, bound: DateBound, today: NaiveDate) -> Option<NaiveDate> {
+    let value = value.trim();
+
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Some(date);
+    }
+
+    if let Some(decade) = parse_decade(value, today) {
+        return match bound {
+            DateBound::Start => NaiveDate::from_ymd_opt(decade, 1, 1),
+            DateBound::End => NaiveDate::from_ymd_opt(decade + 9, 12, 31),
+        };
+    }
+
+    if let Some((year, month)) = value.split_once('-') {
+        let year: i32 = parse_year(year)?;
+        let month: u32 = month.parse().ok()?;
+        let first_day = NaiveDate::from_ymd_opt(year, month, 1)?;
+        return match bound {
+            DateBound::Start => Some(first_day),
+            DateBound::End => first_day.checked_add_months(Months::new(1))?.pred_opt(),
+        };
+    }
+
+    let year = parse_year(value)?;
+    match bound {
+        DateBound::Start => NaiveDate::from_ymd_opt(year, 1, 1),
+        DateBound::End => NaiveDate::from_ymd_opt(year, 12, 31),
+    }
+}
+
+// The bound as YYYY-MM-DD, or the value unchanged when it isn't a date so the filter simply matches nothing
+pub fn normalize_date_bound(value: &str, bound: DateBound) -> String {
+    match parse_date_bound(value, bound, today()) {
+        Some(date) => format_date(date),
+        None => value.trim().to_string(),
+    }
+}
+
+// The same day the given number of years earlier, 29 February becomes the 28th
+pub fn years_before(date: NaiveDate, years: u32) -> Option<NaiveDate> {
+    date.checked_sub_months(Months::new(years.checked_mul(12)?))
+}
+
+pub fn format_date(date: NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+fn parse_year(value: &str) -> Option<i32> {
+    if value.len() != 4 || !value.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    value.parse().ok()
+}
+
+// "1990s", "90s" and "'90s" are all the first year of the decade, 1990
+fn parse_decade(value: &str, today: NaiveDate) -> Option<i32> {
+    let digits = value
+        .trim_start_matches(['\'', '’'])
+        .strip_suffix('s')
+        .map(|x| x.trim_end_matches(['\'', '’']))?;
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let decade: i32 = match digits.len() {
+        4 => digits.parse().ok()?,
+        2 => {
+            let decade_of_century: i32 = digits.parse().ok()?;
+            let current_century = today.year() / 100 * 100;
+            if current_century + decade_of_century <= today.year() {
+                current_century + decade_of_century
+            } else {
+                current_century - 100 + decade_of_century
+            }
+        }
+        _ => return None,
+    };
+
+    if decade % 10 == 0 {
+        Some(decade)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,61 @@
+use crate::model::config::Config;
+use crate::model::embedding_request_body::EmbeddingRequestBody;
+use log::debug;
+use openai_api_rs::v1::embedding::EmbeddingResponse;
+use spinners::{Spinner, Spinners};
+
+pub const EMBEDDING_MODEL: &str = "text-embedding-3-large";
+pub const EMBEDDING_DIMENSIONS: i32 = 1024;
+
+pub async fn create_embeddings(
+    input: Vec<String>,
+    config: &Config,
+) -> Result<EmbeddingResponse, Box<dyn std::error::Error>> {
+    let embedding_request = EmbeddingRequestBody::builder()
+        .input(input)
+        .model(Some(EMBEDDING_MODEL.to_string()))
+        .dimensions(Some(EMBEDDING_DIMENSIONS))
+        .user(Some("ah-scraper".to_string()))
+        .build();
+    debug!("embedding_request: {:?}", embedding_request);
+
+    let body = serde_json::to_string(&embedding_request)?;
+
+    let mut sp = Spinner::new(Spinners::Dots9, "\t\tOpenAI is thinking...".into());
+
+    let client = reqwest::Client::new();
+    let result = client
+        .post(format!(
+            "{}openai/deployments/{}/embeddings?api-version={}",
+            config.open_ai.url, EMBEDDING_MODEL, config.open_ai.api_version
+        ))
+        .header("Content-Type", "application/json")
+        .header("api-key", config.open_ai.key.clone())
+        .body(body)
+        .send()
+        .await?;
+
+    sp.stop();
+
+    let response_body = result.text().await?;
+    debug!("Response Body: {}", response_body);
+
+    let embedding_data: EmbeddingResponse = serde_json::from_str(&response_body)?;
+
+    Ok(embedding_data)
+}
+
+pub async fn create_embedding(
+    input: &str,
+    config: &Config,
+) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+    let mut embedding_data = create_embeddings(vec![input.to_string()], config).await?;
+
+    if embedding_data.data.is_empty() {
+        return Err(Box::<dyn std::error::Error>::from(
+            "No embedding returned.".to_string(),
+        ));
+    }
+
+    Ok(embedding_data.data.swap_remove(0).embedding)
+}
@@ -1,4 +1,14 @@
+pub mod answer_cache_helper;
+pub mod cast_helper;
+pub mod chat_completion_helper;
+pub mod context_helper;
+pub mod criteria_helper;
+pub mod date_helper;
+pub mod embedding_helper;
 pub mod movie_helper;
+pub mod rerank_helper;
 pub mod response_helper;
+pub mod search_index_helper;
+pub mod search_query_helper;
 pub mod tool_helper;
 pub mod vector_math_helper;
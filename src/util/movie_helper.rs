@@ -2,83 +2,250 @@ use crate::model::cosine_similarity::CosineSimilarity;
 use crate::model::{
     cache::Cache,
     movies::{
-        movie::TopRatedMovie, movie_criteria::MovieCriteria, movie_embedding::MovieEmbedding,
+        movie::TopRatedMovie,
+        movie_criteria::{MovieCriteria, SortDirection},
+        movie_embedding::MovieEmbedding,
     },
 };
+use crate::util::cast_helper::take_cast;
+use crate::util::date_helper::{parse_date_bound, today, DateBound};
 use crate::util::vector_math_helper::VectorMathHelper;
-use chrono::prelude::*;
-use log::debug;
+use chrono::NaiveDate;
+use log::{debug, warn};
 use spinners::{Spinner, Spinners};
 use std::{fs, path::Path, sync::Mutex};
 
-pub fn can_load_data(cache: &Mutex<Cache>) -> bool {
-    let current_directory = std::env::current_dir().unwrap();
-
-    let movie_embeddings_path = current_directory.join("src/data/embeddings.json");
-    let top_movies_path = current_directory.join("src/data/topRatedMovies.json");
-
-    if Path::new(&movie_embeddings_path).exists() && Path::new(&top_movies_path).exists() {
-        debug!("Loading data from cache or disk...");
-        let mut sp = Spinner::new(
-            Spinners::Dots9,
-            "\t\tLoading data from cache or disk...".into(),
-        );
-
-        let cache_lock = cache.lock().unwrap();
-        let mut movie_embeddings_lock = cache_lock.movie_embeddings.lock().unwrap();
-        if movie_embeddings_lock.is_empty() {
-            let movie_embeddings_json_content = fs::read_to_string(&movie_embeddings_path).unwrap();
-            let data: Vec<MovieEmbedding> =
-                serde_json::from_str(&movie_embeddings_json_content).unwrap();
+pub fn load_movie_embeddings(cache: &Mutex<Cache>) -> bool {
+    let movie_embeddings_path = std::env::current_dir()
+        .unwrap()
+        .join("src/data/embeddings.json");
+
+    let cache_lock = cache.lock().unwrap();
+    let mut movie_embeddings_lock = cache_lock.movie_embeddings.lock().unwrap();
+
+    // Already seeded, e.g. from the SQLite store
+    if !movie_embeddings_lock.is_empty() {
+        return true;
+    }
+
+    if !Path::new(&movie_embeddings_path).exists() {
+        return false;
+    }
+
+    let mut sp = Spinner::new(
+        Spinners::Dots9,
+        "\t\tLoading embeddings from disk...".into(),
+    );
+    let data: Result<Vec<MovieEmbedding>, Box<dyn std::error::Error>> =
+        read_json(&movie_embeddings_path);
+    sp.stop();
+
+    match data {
+        Ok(data) => {
             *movie_embeddings_lock = data;
+            debug!("Loaded movie embeddings");
+            true
         }
-        debug!("Loaded movie embeddings");
-
-        let mut top_movies_lock = cache_lock.top_movies.lock().unwrap();
-        if top_movies_lock.is_empty() {
-            let top_movies_json_content = fs::read_to_string(&top_movies_path).unwrap();
-            let data: Vec<TopRatedMovie> = serde_json::from_str(&top_movies_json_content).unwrap();
-            *top_movies_lock = data;
+        Err(e) => {
+            warn!("Error loading {:?}: {}", movie_embeddings_path, e);
+            false
         }
-        debug!("Loaded top rated movies");
+    }
+}
+
+pub fn load_top_movies(cache: &Mutex<Cache>) -> bool {
+    let top_movies_path = std::env::current_dir()
+        .unwrap()
+        .join("src/data/topRatedMovies.json");
 
-        debug!("Loaded data from cache or disk");
+    if !Path::new(&top_movies_path).exists() {
+        return false;
+    }
+
+    let cache_lock = cache.lock().unwrap();
+    let mut top_movies_lock = cache_lock.top_movies.lock().unwrap();
+    if top_movies_lock.is_empty() {
+        let mut sp = Spinner::new(Spinners::Dots9, "\t\tLoading movies from disk...".into());
+        let data: Result<Vec<TopRatedMovie>, Box<dyn std::error::Error>> =
+            read_json(&top_movies_path);
         sp.stop();
 
-        true
-    } else {
-        false
+        match data {
+            Ok(data) => *top_movies_lock = data,
+            Err(e) => {
+                warn!("Error loading {:?}: {}", top_movies_path, e);
+                return false;
+            }
+        }
     }
+    debug!("Loaded top rated movies");
+
+    true
 }
 
-pub fn find_similar_movies(
-    movie_id: &str,
-    movie_embeddings: &Vec<MovieEmbedding>,
+// A bad data file is reported rather than panicking while the cache is locked
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(path)?;
+
+    Ok(serde_json::from_str(&content)?)
+}
+
+pub fn load_movie_details(cache: &Mutex<Cache>, movie_id: i32) -> Option<TopRatedMovie> {
+    let cache_lock = cache.lock().unwrap();
+    let mut movie_details_lock = cache_lock.movie_details.lock().unwrap();
+
+    if let Some(movie) = movie_details_lock.get(&movie_id) {
+        return Some(movie.clone());
+    }
+
+    let movie_path = std::env::current_dir()
+        .unwrap()
+        .join(format!("src/data/movies/{}.json", movie_id));
+
+    let mut movie_json: serde_json::Value = match fs::read_to_string(&movie_path) {
+        Ok(movie_json_content) => match serde_json::from_str(&movie_json_content) {
+            Ok(movie_json) => movie_json,
+            Err(e) => {
+                debug!("Error parsing {:?}: {}", movie_path, e);
+                return None;
+            }
+        },
+        Err(_) => return None,
+    };
+
+    // TMDB keeps the cast under credits.cast rather than a top-level cast field
+    let cast = take_cast(&mut movie_json);
+    let mut movie: TopRatedMovie = match serde_json::from_value(movie_json) {
+        Ok(movie) => movie,
+        Err(e) => {
+            debug!("Error parsing {:?}: {}", movie_path, e);
+            return None;
+        }
+    };
+    if movie.cast.is_none() {
+        movie.cast = cast.ok().filter(|x| !x.is_empty());
+    }
+    debug!("Loaded movie details for {}", movie_id);
+
+    movie_details_lock.insert(movie_id, movie.clone());
+
+    Some(movie)
+}
+
+pub fn sort_movies(
+    movies: &mut [TopRatedMovie],
+    field: &str,
+    direction: &SortDirection,
+) -> Result<(), String> {
+    match field {
+        "title" => movies.sort_by_key(|a| a.title.to_lowercase()),
+        "release_date" => movies.sort_by(|a, b| a.release_date.cmp(&b.release_date)),
+        "imdb_score" => movies.sort_by(|a, b| a.imdb_score.total_cmp(&b.imdb_score)),
+        "vote_average" => movies.sort_by(|a, b| a.vote_average.total_cmp(&b.vote_average)),
+        "popularity" => movies.sort_by(|a, b| a.popularity.total_cmp(&b.popularity)),
+        "runtime" => movies.sort_by_key(|x| x.runtime),
+        _ => return Err(format!("Unknown sort field: {}", field)),
+    }
+
+    if matches!(direction, SortDirection::Desc) {
+        movies.reverse();
+    }
+
+    Ok(())
+}
+
+// Ranks every movie by cosine similarity to the embedding, most similar first
+pub fn rank_by_embedding(
+    embedding: &[f32],
+    movie_embeddings: &[MovieEmbedding],
 ) -> Vec<CosineSimilarity> {
-    let movie_embedding_for_comparison = movie_embeddings
+    let mut cosine_similarities: Vec<CosineSimilarity> = movie_embeddings
         .iter()
-        .find(|x| x.movie_id.to_string() == *movie_id)
-        .unwrap();
-
-    let mut cosine_similarities = vec![];
-
-    for movie_embedding in movie_embeddings.iter() {
-        if movie_embedding.movie_id != movie_embedding_for_comparison.movie_id {
-            let result = VectorMathHelper::cosine_similarity(
-                &movie_embedding_for_comparison
-                    .embeddings
-                    .as_ref()
-                    .unwrap()
-                    .data[0]
-                    .embedding,
-                &movie_embedding.embeddings.as_ref().unwrap().data[0].embedding,
-            );
-            cosine_similarities.push(CosineSimilarity {
+        .filter_map(|movie_embedding| {
+            let movie_vector = &movie_embedding.embeddings.as_ref()?.data.first()?.embedding;
+            if movie_vector.len() != embedding.len() {
+                return None;
+            }
+
+            Some(CosineSimilarity {
                 movie_id: movie_embedding.movie_id,
-                similarity: result,
-            });
+                similarity: VectorMathHelper::cosine_similarity(embedding, movie_vector),
+            })
+        })
+        .collect();
+
+    cosine_similarities.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+
+    cosine_similarities
+}
+
+// The stored embedding of a movie, if it has one
+pub fn movie_embedding(movie_embeddings: &[MovieEmbedding], movie_id: i32) -> Option<&[f32]> {
+    movie_embeddings
+        .iter()
+        .find(|x| x.movie_id == movie_id)
+        .and_then(|x| x.embeddings.as_ref())
+        .and_then(|x| x.data.first())
+        .map(|x| x.embedding.as_slice())
+}
+
+/**
+ * Builds a query vector from the movies a user liked and disliked.
+ *
+ * The result is the centroid of the liked embeddings minus the weighted centroid of the disliked ones, so it
+ * points towards what the liked movies share and away from the disliked ones.
+ *
+ * @param liked - Embeddings of the liked movies, at least one.
+ * @param disliked - Embeddings of the disliked movies, may be empty.
+ * @param disliked_weight - How much of the disliked centroid to subtract, usually between 0 and 1.
+ * @returns None when there is nothing liked, the dimensions differ or the vectors cancel out.
+ */
+pub fn preference_vector(
+    liked: &[&[f32]],
+    disliked: &[&[f32]],
+    disliked_weight: f32,
+) -> Option<Vec<f32>> {
+    let dimensions = liked.first()?.len();
+    if liked
+        .iter()
+        .chain(disliked.iter())
+        .any(|x| x.len() != dimensions)
+    {
+        return None;
+    }
+
+    let mut vector = VectorMathHelper::centroid(liked);
+    if !disliked.is_empty() {
+        let disliked_centroid = VectorMathHelper::centroid(disliked);
+        for (value, disliked_value) in vector.iter_mut().zip(disliked_centroid) {
+            *value -= disliked_weight * disliked_value;
         }
     }
+
+    // Cosine similarity is undefined for a zero vector
+    if VectorMathHelper::magnitude(&vector) < f32::EPSILON {
+        return None;
+    }
+
+    Some(vector)
+}
+
+pub fn find_similar_movies(
+    movie_id: &str,
+    movie_embeddings: &[MovieEmbedding],
+) -> Vec<CosineSimilarity> {
+    // An unknown movie, or one without an embedding, has nothing similar
+    let movie_id = match movie_id.parse::<i32>() {
+        Ok(movie_id) => movie_id,
+        Err(_) => return Vec::new(),
+    };
+    let embedding = match movie_embedding(movie_embeddings, movie_id) {
+        Some(embedding) => embedding,
+        None => return Vec::new(),
+    };
+
+    let mut cosine_similarities = rank_by_embedding(embedding, movie_embeddings);
+    cosine_similarities.retain(|x| x.movie_id != movie_id);
     debug!("Found {} similar movies", cosine_similarities.len());
 
     cosine_similarities
@@ -119,34 +286,42 @@ pub async fn filter_movies(
     }
     debug!("{} movies left after mpaa filter", filtered_movies.len());
 
-    if let Some(release_date_min) = criteria.release_date_min {
-        filtered_movies = filtered_movies
-            .iter()
-            .filter(|m| {
-                // debug!("Release Date String: {}", m.release_date);
-
-                if let Ok(parsed_date) = Utc.datetime_from_str(&m.release_date, "%Y-%m-%d") {
-                    debug!("Parsed Date: {:?}", parsed_date);
-
-                    if let Ok(min_date) = Utc.datetime_from_str(&release_date_min, "%Y-%m-%d") {
-                        debug!("Min Date: {:?}", min_date);
-
-                        let result = parsed_date >= min_date;
+    if let Some(genres_any) = criteria.genres_any {
+        filtered_movies.retain(|m| genres_any.iter().any(|g| has_genre(m, g)));
+    }
+    if let Some(genres_all) = criteria.genres_all {
+        filtered_movies.retain(|m| genres_all.iter().all(|g| has_genre(m, g)));
+    }
+    if let Some(genres_exclude) = criteria.genres_exclude {
+        filtered_movies.retain(|m| !genres_exclude.iter().any(|g| has_genre(m, g)));
+    }
+    debug!(
+        "{} movies left after genre list filters",
+        filtered_movies.len()
+    );
 
-                        debug!("Result of Comparison: {}", result);
+    if let Some(mpaa_any) = criteria.mpaa_any {
+        filtered_movies.retain(|m| mpaa_any.contains(&m.mpaa));
+    }
+    if let Some(mpaa_exclude) = criteria.mpaa_exclude {
+        filtered_movies.retain(|m| !mpaa_exclude.contains(&m.mpaa));
+    }
+    debug!(
+        "{} movies left after mpaa list filters",
+        filtered_movies.len()
+    );
 
-                        return result;
-                    }
-                } else {
-                    debug!(
-                        "Error parsing date: {:?}",
-                        Utc.datetime_from_str(&m.release_date, "%Y-%m-%d")
-                    );
-                }
-                false
-            })
-            .cloned()
-            .collect();
+    // Release dates compare as calendar dates; a year or decade covers all of its days
+    let today = today();
+    if let Some(release_date_min) = criteria.release_date_min {
+        match parse_date_bound(&release_date_min, DateBound::Start, today) {
+            Some(min_date) => filtered_movies
+                .retain(|m| release_date(m).is_some_and(|release_date| release_date >= min_date)),
+            None => {
+                debug!("Error parsing min release date: {}", release_date_min);
+                filtered_movies.clear();
+            }
+        }
     }
     debug!(
         "{} movies left after min release date filter",
@@ -154,22 +329,14 @@ pub async fn filter_movies(
     );
 
     if let Some(release_date_max) = criteria.release_date_max {
-        filtered_movies = filtered_movies
-            .iter()
-            .filter(
-                |m| match Utc.datetime_from_str(&m.release_date, "%Y-%m-%d") {
-                    Ok(parsed_date) => {
-                        if let Ok(max_date) = Utc.datetime_from_str(&release_date_max, "%Y-%m-%d") {
-                            parsed_date >= max_date
-                        } else {
-                            false
-                        }
-                    }
-                    Err(_) => false,
-                },
-            )
-            .cloned()
-            .collect();
+        match parse_date_bound(&release_date_max, DateBound::End, today) {
+            Some(max_date) => filtered_movies
+                .retain(|m| release_date(m).is_some_and(|release_date| release_date <= max_date)),
+            None => {
+                debug!("Error parsing max release date: {}", release_date_max);
+                filtered_movies.clear();
+            }
+        }
     }
     debug!(
         "{} movies left after max release date filter",
@@ -212,5 +379,79 @@ pub async fn filter_movies(
         filtered_movies.len()
     );
 
+    if let Some(keywords) = criteria.keywords {
+        filtered_movies.retain(|m| keywords.iter().all(|k| has_keyword(m, k)));
+    }
+    if let Some(keywords_exclude) = criteria.keywords_exclude {
+        filtered_movies.retain(|m| !keywords_exclude.iter().any(|k| has_keyword(m, k)));
+    }
+    debug!(
+        "{} movies left after keyword filters",
+        filtered_movies.len()
+    );
+
+    // TMDB uses 0 for an unknown budget or revenue
+    if criteria.budget_min.is_some() || criteria.budget_max.is_some() {
+        filtered_movies.retain(|m| {
+            in_range(
+                m.budget.filter(|x| *x > 0).map(u64::from),
+                criteria.budget_min,
+                criteria.budget_max,
+            )
+        });
+    }
+    debug!("{} movies left after budget filter", filtered_movies.len());
+
+    if criteria.revenue_min.is_some() || criteria.revenue_max.is_some() {
+        filtered_movies.retain(|m| {
+            in_range(
+                m.revenue.filter(|x| *x > 0).map(u64::from),
+                criteria.revenue_min,
+                criteria.revenue_max,
+            )
+        });
+    }
+    debug!("{} movies left after revenue filter", filtered_movies.len());
+
+    if let Some(vote_count_min) = criteria.vote_count_min {
+        filtered_movies.retain(|m| m.vote_count >= vote_count_min);
+    }
+    if criteria.popularity_min.is_some() || criteria.popularity_max.is_some() {
+        filtered_movies.retain(|m| {
+            in_range(
+                Some(m.popularity),
+                criteria.popularity_min,
+                criteria.popularity_max,
+            )
+        });
+    }
+    debug!(
+        "{} movies left after vote count and popularity filters",
+        filtered_movies.len()
+    );
+
     filtered_movies.to_vec()
 }
+
+fn release_date(movie: &TopRatedMovie) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(&movie.release_date, "%Y-%m-%d").ok()
+}
+
+fn has_genre(movie: &TopRatedMovie, genre: &str) -> bool {
+    movie.genres.iter().any(|x| x.eq_ignore_ascii_case(genre))
+}
+
+fn has_keyword(movie: &TopRatedMovie, keyword: &str) -> bool {
+    movie
+        .keywords
+        .as_ref()
+        .is_some_and(|keywords| keywords.iter().any(|x| x.eq_ignore_ascii_case(keyword)))
+}
+
+// An unknown value is never in range
+fn in_range<T: PartialOrd>(value: Option<T>, min: Option<T>, max: Option<T>) -> bool {
+    match value {
+        Some(value) => min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max),
+        None => false,
+    }
+}
@@ -0,0 +1,301 @@
+use crate::model::movies::movie::TopRatedMovie;
+use crate::util::vector_math_helper::VectorMathHelper;
+use log::debug;
+use std::collections::HashMap;
+
+// A movie up for re-ranking, with its similarity to the query and its own embedding
+pub struct RankedMovie {
+    pub movie: TopRatedMovie,
+    pub similarity: f32,
+    pub embedding: Vec<f32>,
+}
+
+#[derive(Debug, Default)]
+pub struct RerankOptions {
+    // 0 keeps the similarity order, 1 only looks at how different a movie is from the ones already picked
+    pub diversity: Option<f32>,
+    pub max_per_genre: Option<usize>,
+    pub max_per_franchise: Option<usize>,
+}
+
+impl RerankOptions {
+    pub fn is_empty(&self) -> bool {
+        self.diversity.is_none() && self.max_per_genre.is_none() && self.max_per_franchise.is_none()
+    }
+}
+
+/**
+ * Picks `limit` movies from the candidates using maximal marginal relevance and the per-genre and per-franchise
+ * caps.
+ *
+ * Each step picks the candidate with the best `(1 - diversity) * similarity - diversity * max_overlap`, where
+ * `max_overlap` is its highest cosine similarity to a movie already picked. Only the newly picked movie is compared
+ * against the rest, so a step costs one pass over the candidates. Without a diversity setting this is the plain
+ * similarity order. Candidates that would go over a cap are skipped.
+ *
+ * @param candidates - The movies to choose from, usually a pool several times larger than `limit`.
+ * @param options - The diversity trade-off and caps.
+ * @param limit - How many movies to pick.
+ */
+pub fn rerank(
+    candidates: Vec<RankedMovie>,
+    options: &RerankOptions,
+    limit: usize,
+) -> Vec<RankedMovie> {
+    let diversity = options.diversity.unwrap_or(0.0);
+    let mut remaining: Vec<(RankedMovie, String)> = candidates
+        .into_iter()
+        .map(|x| {
+            let franchise = franchise_keyword(&x.movie.title);
+            (x, franchise)
+        })
+        .collect();
+    // Each remaining candidate's highest similarity to a movie already picked, updated as movies are picked
+    let mut max_overlaps: Vec<f32> = vec![0.0; remaining.len()];
+    let mut selected: Vec<RankedMovie> = Vec::new();
+    let mut genre_counts: HashMap<String, usize> = HashMap::new();
+    let mut franchise_counts: HashMap<String, usize> = HashMap::new();
+
+    while selected.len() < limit {
+        let mut best: Option<(usize, f32)> = None;
+
+        for (index, (candidate, franchise)) in remaining.iter().enumerate() {
+            if exceeds_cap(
+                &genre_counts,
+                primary_genre(&candidate.movie),
+                options.max_per_genre,
+            ) || exceeds_cap(
+                &franchise_counts,
+                Some(franchise.clone()),
+                options.max_per_franchise,
+            ) {
+                continue;
+            }
+
+            let score = (1.0 - diversity) * candidate.similarity - diversity * max_overlaps[index];
+
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((index, score));
+            }
+        }
+
+        let Some((index, _)) = best else {
+            break;
+        };
+        let (picked, franchise) = remaining.remove(index);
+        max_overlaps.remove(index);
+
+        if diversity > 0.0 {
+            for ((candidate, _), max_overlap) in remaining.iter().zip(max_overlaps.iter_mut()) {
+                let overlap =
+                    VectorMathHelper::cosine_similarity(&candidate.embedding, &picked.embedding);
+                *max_overlap = max_overlap.max(overlap);
+            }
+        }
+
+        if let Some(genre) = primary_genre(&picked.movie) {
+            *genre_counts.entry(genre).or_insert(0) += 1;
+        }
+        *franchise_counts.entry(franchise).or_insert(0) += 1;
+        selected.push(picked);
+    }
+    debug!("Re-ranked to {} movies with {:?}", selected.len(), options);
+
+    selected
+}
+
+/**
+ * Guesses a movie's franchise from its title: the first two words that aren't function words, before any subtitle
+ * and ignoring sequel markers, or the whole title when it has only one such word. "Toy Story 2", "Harry Potter and
+ * the Goblet of Fire", "The Dark Knight Rises" and "The Godfather Part II" group with the rest of their series
+ * while "The Lord of the Rings" and "Lord of War" don't.
+ */
+pub fn franchise_keyword(title: &str) -> String {
+    // "Don't" and "King's" are one word, not a word and a fragment
+    let main_title = title
+        .split(':')
+        .next()
+        .unwrap_or(title)
+        .to_lowercase()
+        .replace(['\'', '\u{2019}'], "");
+    let words: Vec<&str> = main_title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|x| !x.is_empty())
+        .collect();
+
+    let keywords: Vec<&str> = words
+        .iter()
+        .copied()
+        .filter(|x| !is_function_word(x) && (x.len() > 2 || x.chars().all(|c| c.is_ascii_digit())))
+        .collect();
+    let keywords = without_sequel_markers(&keywords);
+    if keywords.len() >= 2 {
+        return keywords[..2].join(" ");
+    }
+
+    // A one word franchise is the whole title, so "Wild" and "Into the Wild" stay apart
+    without_sequel_markers(&words).join(" ")
+}
+
+// "Rocky II", "The Godfather Part II" and their first movies are the same franchise
+fn without_sequel_markers<'a>(words: &[&'a str]) -> Vec<&'a str> {
+    words
+        .iter()
+        .enumerate()
+        .filter(|(index, word)| *index == 0 || !is_sequel_marker(word))
+        .map(|(_, word)| *word)
+        .collect()
+}
+
+fn is_sequel_marker(word: &str) -> bool {
+    word.chars().all(|c| c.is_ascii_digit())
+        || matches!(
+            word,
+            "ii" | "iii" | "iv" | "v" | "vi" | "vii" | "viii" | "ix" | "x"
+        )
+        || matches!(word, "part" | "chapter" | "vol" | "volume")
+}
+
+// Words shared by unrelated titles, like "How to Train Your Dragon" and "How to Lose a Guy in 10 Days"
+fn is_function_word(word: &str) -> bool {
+    matches!(
+        word,
+        "the"
+            | "a"
+            | "an"
+            | "and"
+            | "or"
+            | "of"
+            | "in"
+            | "on"
+            | "at"
+            | "to"
+            | "for"
+            | "with"
+            | "from"
+            | "by"
+            | "into"
+            | "about"
+            | "who"
+            | "what"
+            | "how"
+            | "why"
+            | "when"
+            | "where"
+            | "is"
+            | "are"
+            | "was"
+            | "be"
+            | "it"
+            | "its"
+            | "my"
+            | "your"
+            | "his"
+            | "her"
+            | "their"
+            | "our"
+            | "we"
+            | "you"
+            | "me"
+            | "not"
+            | "no"
+            | "dont"
+    )
+}
+
+// TMDB lists the main genre first
+fn primary_genre(movie: &TopRatedMovie) -> Option<String> {
+    movie.genres.first().cloned()
+}
+
+fn exceeds_cap(counts: &HashMap<String, usize>, key: Option<String>, cap: Option<usize>) -> bool {
+    match (key, cap) {
+        (Some(key), Some(cap)) => counts.get(&key).copied().unwrap_or(0) >= cap,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn same_franchise(a: &str, b: &str) -> bool {
+        franchise_keyword(a) == franchise_keyword(b)
+    }
+
+    #[test]
+    fn numbered_sequels_group_with_the_first_movie() {
+        assert!(same_franchise("Toy Story", "Toy Story 2"));
+        assert!(same_franchise("Rocky", "Rocky II"));
+        assert!(same_franchise("Kill Bill: Vol. 1", "Kill Bill: Vol. 2"));
+        assert!(same_franchise("Deadpool", "Deadpool 2"));
+        assert!(same_franchise("Saw", "Saw X"));
+    }
+
+    #[test]
+    fn part_and_chapter_sequels_group_with_the_first_movie() {
+        assert!(same_franchise("The Godfather", "The Godfather Part II"));
+        assert!(same_franchise("John Wick", "John Wick: Chapter 2"));
+    }
+
+    #[test]
+    fn subtitles_and_function_words_are_ignored() {
+        assert!(same_franchise(
+            "The Lord of the Rings: The Fellowship of the Ring",
+            "The Lord of the Rings: The Return of the King"
+        ));
+        assert!(same_franchise(
+            "Harry Potter and the Philosopher's Stone",
+            "Harry Potter and the Goblet of Fire"
+        ));
+        assert!(same_franchise("The Dark Knight", "The Dark Knight Rises"));
+        assert_eq!(
+            franchise_keyword("How to Train Your Dragon"),
+            "train dragon"
+        );
+    }
+
+    #[test]
+    fn shared_function_words_dont_make_a_franchise() {
+        assert!(!same_franchise(
+            "The Lord of the Rings: The Two Towers",
+            "Lord of War"
+        ));
+        assert!(!same_franchise(
+            "How to Train Your Dragon",
+            "How to Lose a Guy in 10 Days"
+        ));
+        assert!(!same_franchise(
+            "Return of the Jedi",
+            "The Return of the Living Dead"
+        ));
+        assert!(!same_franchise(
+            "In the Mood for Love",
+            "In the Heat of the Night"
+        ));
+        assert!(!same_franchise(
+            "The Man Who Knew Too Much",
+            "The Man Who Shot Liberty Valance"
+        ));
+        assert!(!same_franchise(
+            "The Girl with the Dragon Tattoo",
+            "Girl with a Pearl Earring"
+        ));
+        assert!(!same_franchise("Heat", "The Heat"));
+        assert!(!same_franchise("Wild", "Into the Wild"));
+        assert!(!same_franchise("About Time", "In Time"));
+    }
+
+    #[test]
+    fn contractions_and_possessives_are_one_word() {
+        assert!(!same_franchise("Don't Look Now", "Don't Breathe"));
+        assert!(!same_franchise("The King's Speech", "The King's Man"));
+        assert!(same_franchise("Schindler's List", "Schindler’s List"));
+    }
+
+    #[test]
+    fn titles_of_only_short_or_function_words_keep_them() {
+        assert_eq!(franchise_keyword("Up"), "up");
+        assert_eq!(franchise_keyword("12 Angry Men"), "12 angry");
+    }
+}
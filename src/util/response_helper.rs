@@ -1,7 +1,9 @@
 use crate::model::{
-    chat_completion_response::{ChatCompletionChoice, ChatCompletionResponse},
+    chat_completion_response::{ChatCompletionChoice, ChatCompletionResponse, ToolCall},
     movies::movie_criteria::MovieCriteria,
 };
+use crate::util::criteria_helper::parse_criteria;
+use crate::util::tool_helper::FILTER_TOOL_NAME;
 use log::debug;
 use serde::Deserialize;
 
@@ -34,32 +36,48 @@ pub fn handle_choices(choices: &Vec<ChatCompletionChoice>) -> String {
 
 #[derive(Debug, Deserialize)]
 struct RootObject {
-    movie_criteria: MovieCriteria,
+    movie_criteria: serde_json::Value,
 }
 
-pub fn handle_tool_calls(choices: &Vec<ChatCompletionChoice>) -> Option<MovieCriteria> {
-    for choice in choices {
-        if let Some(tool_calls) = &choice.message.tool_calls {
-            for call in tool_calls {
-                if let Some(arguments) = &call.function.arguments {
-                    debug!("Arguments: {:?}", arguments);
-                    let arguments_str: &str = arguments;
-                    debug!("Arguments str: {}", arguments_str);
+pub fn handle_tool_calls(choices: &[ChatCompletionChoice]) -> Option<MovieCriteria> {
+    // The first filter_movies call with usable arguments wins, broken ones don't hide later calls
+    collect_tool_calls(choices)
+        .into_iter()
+        .filter(|call| call.function.name.as_deref() == Some(FILTER_TOOL_NAME))
+        .find_map(|call| match parse_filter_arguments(call) {
+            Ok(movie_criteria) => {
+                debug!("MovieCriteria: {:?}", movie_criteria);
+                Some(movie_criteria)
+            }
+            Err(e) => {
+                debug!("Error deserializing MovieCriteria from {}: {}", call.id, e);
+                None
+            }
+        })
+}
+
+// Every tool call across the choices, in the order the model made them
+pub fn collect_tool_calls(choices: &[ChatCompletionChoice]) -> Vec<&ToolCall> {
+    choices
+        .iter()
+        .filter_map(|choice| choice.message.tool_calls.as_ref())
+        .flatten()
+        .collect()
+}
 
-                    match serde_json::from_str::<RootObject>(arguments_str) {
-                        Ok(movie_criteria) => {
-                            debug!("MovieCriteria: {:?}", movie_criteria.movie_criteria);
+pub fn parse_filter_arguments(call: &ToolCall) -> Result<MovieCriteria, String> {
+    let arguments = call.function.arguments.as_deref().unwrap_or_default();
+    debug!("Arguments: {:?}", arguments);
 
-                            return Some(movie_criteria.movie_criteria);
-                        }
-                        Err(e) => {
-                            debug!("Error deserializing MovieCriteria: {:?}", e);
-                            return None;
-                        }
-                    }
-                }
-            }
-        }
-    }
-    None
+    let root = serde_json::from_str::<RootObject>(arguments)
+        .map_err(|e| format!("Invalid filter_movies arguments: {}", e))?;
+
+    // The same checks as /api/movieCriteria, so unknown or misspelled fields are reported instead of dropped
+    parse_criteria(&root.movie_criteria.to_string()).map_err(|errors| {
+        errors
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<String>>()
+            .join("; ")
+    })
 }
@@ -0,0 +1,220 @@
+use crate::model::config::AzureSearchConfig;
+use crate::model::movies::movie::{Movie, TopRatedMovie};
+use crate::util::embedding_helper::EMBEDDING_DIMENSIONS;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Azure Search accepts up to 1000 documents or 16 MB per batch; each of ours carries a 1024-float vector
+const INDEX_BATCH_SIZE: usize = 100;
+
+#[derive(Serialize, Debug, Default)]
+pub struct IndexReport {
+    pub documents_indexed: usize,
+    pub documents_failed: usize,
+    pub documents_without_embedding: usize,
+    pub failures: Vec<IndexFailure>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct IndexFailure {
+    pub key: String,
+    pub status_code: u16,
+    pub message: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct IndexResponse {
+    value: Vec<IndexResult>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct IndexResult {
+    key: String,
+    status: bool,
+    error_message: Option<String>,
+    status_code: u16,
+}
+
+/**
+ * Creates the idx-movies index, or updates it in place when it already exists.
+ *
+ * The fields mirror `Movie`, plus an `embedding` vector field sized to our embeddings.
+ */
+pub async fn create_or_update_movie_index(
+    config: &AzureSearchConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let client = reqwest::Client::new();
+
+    let response = client
+        .put(format!(
+            "{}indexes/idx-movies?api-version={}",
+            config.url, config.api_version
+        ))
+        .header("Content-Type", "application/json")
+        .header("api-key", config.key.clone())
+        .body(movie_index_definition().to_string())
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await?;
+        return Err(format!(
+            "Failed to create or update idx-movies ({}): {}",
+            status, body
+        )
+        .into());
+    }
+    info!("Created or updated idx-movies ({})", status);
+
+    Ok(())
+}
+
+/**
+ * Uploads the movies to idx-movies in batches with mergeOrUpload, so existing documents are updated in place.
+ *
+ * A batch that's rejected outright is recorded against every document in it and the upload carries on.
+ *
+ * @param movies - The documents to upload.
+ * @param embeddings - Embedding vectors keyed by movie ID. Movies without one are uploaded without a vector.
+ */
+pub async fn upload_movies(
+    config: &AzureSearchConfig,
+    movies: Vec<TopRatedMovie>,
+    embeddings: &HashMap<i32, Vec<f32>>,
+) -> Result<IndexReport, Box<dyn std::error::Error>> {
+    let client = reqwest::Client::new();
+    let mut report = IndexReport::default();
+
+    let mut documents = Vec::new();
+    for movie in movies {
+        let movie_id = movie.id;
+        let mut document = serde_json::to_value(Movie::from(movie))?;
+        document["@search.action"] = serde_json::json!("mergeOrUpload");
+
+        match embeddings.get(&movie_id) {
+            Some(embedding) if embedding.len() == EMBEDDING_DIMENSIONS as usize => {
+                document["embedding"] = serde_json::json!(embedding);
+            }
+            Some(embedding) => {
+                warn!(
+                    "Skipping embedding for movie {} with {} dimensions",
+                    movie_id,
+                    embedding.len()
+                );
+                report.documents_without_embedding += 1;
+            }
+            None => report.documents_without_embedding += 1,
+        }
+
+        documents.push(document);
+    }
+
+    for batch in documents.chunks(INDEX_BATCH_SIZE) {
+        let response = client
+            .post(format!(
+                "{}indexes/idx-movies/docs/index?api-version={}",
+                config.url, config.api_version
+            ))
+            .header("Content-Type", "application/json")
+            .header("api-key", config.key.clone())
+            .body(serde_json::json!({ "value": batch }).to_string())
+            .send()
+            .await?;
+
+        // 207 means some documents in the batch failed; the body lists each one
+        let status = response.status();
+        let body = response.text().await?;
+        debug!("Index batch of {} returned {}", batch.len(), status);
+
+        match serde_json::from_str::<IndexResponse>(&body) {
+            Ok(index_response) if status.is_success() => {
+                for result in index_response.value {
+                    if result.status {
+                        report.documents_indexed += 1;
+                    } else {
+                        report.documents_failed += 1;
+                        report.failures.push(IndexFailure {
+                            key: result.key,
+                            status_code: result.status_code,
+                            message: result.error_message.unwrap_or_default(),
+                        });
+                    }
+                }
+            }
+            _ => {
+                warn!("Index batch rejected ({}): {}", status, body);
+                for document in batch {
+                    report.documents_failed += 1;
+                    report.failures.push(IndexFailure {
+                        key: document["id"].as_str().unwrap_or_default().to_string(),
+                        status_code: status.as_u16(),
+                        message: body.clone(),
+                    });
+                }
+            }
+        }
+    }
+    info!(
+        "Indexed {} movies, {} failed",
+        report.documents_indexed, report.documents_failed
+    );
+
+    Ok(report)
+}
+
+fn movie_index_definition() -> serde_json::Value {
+    serde_json::json!({
+        "name": "idx-movies",
+        "fields": [
+            { "name": "id", "type": "Edm.String", "key": true, "filterable": true },
+            { "name": "title", "type": "Edm.String", "searchable": true, "sortable": true },
+            { "name": "backdrop_path", "type": "Edm.String" },
+            { "name": "poster_path", "type": "Edm.String" },
+            { "name": "release_date", "type": "Edm.String", "filterable": true, "sortable": true },
+            { "name": "vote_average", "type": "Edm.Double", "filterable": true, "sortable": true },
+            { "name": "vote_count", "type": "Edm.Int64", "filterable": true, "sortable": true },
+            { "name": "popularity", "type": "Edm.Double", "filterable": true, "sortable": true },
+            { "name": "overview", "type": "Edm.String", "searchable": true },
+            { "name": "imdb_id", "type": "Edm.String", "filterable": true },
+            { "name": "budget", "type": "Edm.Int64", "filterable": true, "sortable": true },
+            { "name": "homepage", "type": "Edm.String" },
+            { "name": "revenue", "type": "Edm.Int64", "filterable": true, "sortable": true },
+            { "name": "runtime", "type": "Edm.Int32", "filterable": true, "sortable": true },
+            { "name": "tagline", "type": "Edm.String", "searchable": true },
+            { "name": "genres", "type": "Collection(Edm.String)", "searchable": true, "filterable": true, "facetable": true },
+            {
+                "name": "cast",
+                "type": "Collection(Edm.ComplexType)",
+                "fields": [
+                    { "name": "name", "type": "Edm.String", "searchable": true, "filterable": true },
+                    { "name": "character", "type": "Edm.String", "searchable": true },
+                    { "name": "profile_path", "type": "Edm.String" }
+                ]
+            },
+            { "name": "keywords", "type": "Collection(Edm.String)", "searchable": true, "filterable": true, "facetable": true },
+            { "name": "mpaa", "type": "Edm.String", "filterable": true, "facetable": true },
+            { "name": "summaries", "type": "Collection(Edm.String)", "searchable": true },
+            { "name": "synopsis", "type": "Edm.String", "searchable": true },
+            { "name": "imdb_score", "type": "Edm.Double", "filterable": true, "sortable": true },
+            {
+                "name": "embedding",
+                "type": "Collection(Edm.Single)",
+                "searchable": true,
+                "retrievable": false,
+                "dimensions": EMBEDDING_DIMENSIONS,
+                "vectorSearchProfile": "movie-vector-profile"
+            }
+        ],
+        "vectorSearch": {
+            "algorithms": [
+                { "name": "movie-hnsw", "kind": "hnsw", "hnswParameters": { "metric": "cosine" } }
+            ],
+            "profiles": [
+                { "name": "movie-vector-profile", "algorithm": "movie-hnsw" }
+            ]
+        }
+    })
+}
@@ -0,0 +1,402 @@
+use crate::model::movies::cast_index::normalize_name;
+use crate::model::movies::movie_criteria::MovieCriteria;
+use crate::util::date_helper::{normalize_date_bound, DateBound};
+use log::debug;
+
+/**
+ * Builds an Azure Search request body for the idx-movies index from the criteria.
+ *
+ * Text goes into `search`, everything else into an OData `filter`. Results are ordered by relevance
+ * when there's search text and by IMDb score otherwise. Paging (`top`/`skip`) is left to the caller.
+ *
+ * @param criteria - The criteria to translate.
+ */
+pub fn criteria_to_search_request(criteria: &MovieCriteria) -> serde_json::Value {
+    let search_text = criteria
+        .search
+        .as_deref()
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty());
+
+    let mut body = serde_json::json!({
+        "search": search_text.unwrap_or("*"),
+        "searchMode": "all",
+        "orderby": match search_text {
+            Some(_) => "search.score() desc, imdb_score desc",
+            None => "imdb_score desc",
+        },
+    });
+
+    if let Some(filter) = criteria_to_filter(criteria) {
+        body["filter"] = serde_json::Value::String(filter);
+    }
+    debug!("Azure Search request: {}", body);
+
+    body
+}
+
+pub fn criteria_to_filter(criteria: &MovieCriteria) -> Option<String> {
+    let mut clauses: Vec<String> = Vec::new();
+
+    if let Some(genre) = &criteria.genre {
+        // The index stores TMDB's capitalised names ("Science Fiction") and search.in is case-sensitive
+        let genres: Vec<String> = genre
+            .split(',')
+            .map(|g| g.trim())
+            .filter(|g| !g.is_empty())
+            .map(title_case)
+            .collect();
+        if !genres.is_empty() {
+            clauses.push(format!(
+                "genres/any(g: search.in(g, {}, '|'))",
+                quote(&genres.join("|"))
+            ));
+        }
+    }
+
+    if let Some(genres_any) = &criteria.genres_any {
+        clauses.push(format!(
+            "genres/any(g: search.in(g, {}, '|'))",
+            quote(&title_case_all(genres_any).join("|"))
+        ));
+    }
+
+    if let Some(genres_all) = &criteria.genres_all {
+        for genre in title_case_all(genres_all) {
+            clauses.push(format!("genres/any(g: g eq {})", quote(&genre)));
+        }
+    }
+
+    if let Some(genres_exclude) = &criteria.genres_exclude {
+        clauses.push(format!(
+            "not genres/any(g: search.in(g, {}, '|'))",
+            quote(&title_case_all(genres_exclude).join("|"))
+        ));
+    }
+
+    if let Some(mpaa) = &criteria.mpaa {
+        clauses.push(format!("mpaa eq {}", quote(mpaa.trim())));
+    }
+
+    if let Some(mpaa_any) = &criteria.mpaa_any {
+        clauses.push(format!(
+            "search.in(mpaa, {}, '|')",
+            quote(&mpaa_any.join("|"))
+        ));
+    }
+
+    if let Some(mpaa_exclude) = &criteria.mpaa_exclude {
+        clauses.push(format!(
+            "not search.in(mpaa, {}, '|')",
+            quote(&mpaa_exclude.join("|"))
+        ));
+    }
+
+    // One clause per actor so each must be in the cast; Lucene's fuzzy operator allows the same typos as
+    // the local cast index
+    if let Some(cast) = &criteria.cast {
+        for name in cast {
+            let query = fuzzy_name_query(name);
+            if !query.is_empty() {
+                clauses.push(format!(
+                    "search.ismatch({}, 'cast/name', 'full', 'all')",
+                    quote(&query)
+                ));
+            }
+        }
+    }
+
+    // release_date is a YYYY-MM-DD string, which orders the same as the date
+    if let Some(release_date_min) = &criteria.release_date_min {
+        clauses.push(format!(
+            "release_date ge {}",
+            quote(&normalize_date_bound(release_date_min, DateBound::Start))
+        ));
+    }
+
+    if let Some(release_date_max) = &criteria.release_date_max {
+        clauses.push(format!(
+            "release_date le {}",
+            quote(&normalize_date_bound(release_date_max, DateBound::End))
+        ));
+    }
+
+    if let Some(score_min) = criteria.score_min {
+        clauses.push(format!("imdb_score ge {}", score_min));
+    }
+
+    if let Some(score_max) = criteria.score_max {
+        clauses.push(format!("imdb_score le {}", score_max));
+    }
+
+    // TMDB keywords are stored lowercase
+    if let Some(keywords) = &criteria.keywords {
+        for keyword in keywords {
+            clauses.push(format!(
+                "keywords/any(k: k eq {})",
+                quote(&keyword.to_lowercase())
+            ));
+        }
+    }
+
+    if let Some(keywords_exclude) = &criteria.keywords_exclude {
+        let keywords: Vec<String> = keywords_exclude.iter().map(|x| x.to_lowercase()).collect();
+        clauses.push(format!(
+            "not keywords/any(k: search.in(k, {}, '|'))",
+            quote(&keywords.join("|"))
+        ));
+    }
+
+    // TMDB uses 0 for an unknown budget or revenue, which never matches a range
+    if criteria.budget_min.is_some() || criteria.budget_max.is_some() {
+        clauses.push(String::from("budget gt 0"));
+        push_range(
+            "budget",
+            criteria.budget_min,
+            criteria.budget_max,
+            &mut clauses,
+        );
+    }
+
+    if criteria.revenue_min.is_some() || criteria.revenue_max.is_some() {
+        clauses.push(String::from("revenue gt 0"));
+        push_range(
+            "revenue",
+            criteria.revenue_min,
+            criteria.revenue_max,
+            &mut clauses,
+        );
+    }
+
+    push_range("vote_count", criteria.vote_count_min, None, &mut clauses);
+    push_range(
+        "popularity",
+        criteria.popularity_min,
+        criteria.popularity_max,
+        &mut clauses,
+    );
+
+    if clauses.is_empty() {
+        None
+    } else {
+        Some(clauses.join(" and "))
+    }
+}
+
+fn push_range<T: std::fmt::Display>(
+    field: &str,
+    min: Option<T>,
+    max: Option<T>,
+    clauses: &mut Vec<String>,
+) {
+    if let Some(min) = min {
+        clauses.push(format!("{} ge {}", field, min));
+    }
+
+    if let Some(max) = max {
+        clauses.push(format!("{} le {}", field, max));
+    }
+}
+
+// OData string literals escape a single quote by doubling it
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+// normalize_name leaves only letters, digits and spaces, so nothing needs escaping for Lucene
+fn fuzzy_name_query(name: &str) -> String {
+    normalize_name(name)
+        .split(' ')
+        .filter(|x| !x.is_empty())
+        .map(|word| match word.chars().count() {
+            0..=3 => word.to_string(),
+            4..=7 => format!("{}~1", word),
+            _ => format!("{}~2", word),
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn title_case_all(values: &[String]) -> Vec<String> {
+    values
+        .iter()
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+        .map(title_case)
+        .collect()
+}
+
+fn title_case(value: &str) -> String {
+    // The one TMDB genre that isn't plain title case
+    if value.eq_ignore_ascii_case("tv movie") {
+        return String::from("TV Movie");
+    }
+
+    value
+        .split_whitespace()
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first
+                    .to_uppercase()
+                    .chain(chars.flat_map(|c| c.to_lowercase()))
+                    .collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn criteria(value: serde_json::Value) -> MovieCriteria {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn filter(value: serde_json::Value) -> String {
+        criteria_to_filter(&criteria(value)).unwrap()
+    }
+
+    #[test]
+    fn empty_criteria_match_everything_by_score() {
+        let request = criteria_to_search_request(&criteria(json!({})));
+
+        assert_eq!(
+            request,
+            json!({"search": "*", "searchMode": "all", "orderby": "imdb_score desc"})
+        );
+    }
+
+    #[test]
+    fn search_text_orders_by_relevance() {
+        let request = criteria_to_search_request(&criteria(json!({
+            "search": "  heist  ",
+            "score_min": 7.5
+        })));
+
+        assert_eq!(request["search"], "heist");
+        assert_eq!(request["orderby"], "search.score() desc, imdb_score desc");
+        assert_eq!(request["filter"], "imdb_score ge 7.5");
+    }
+
+    #[test]
+    fn blank_search_text_is_a_wildcard() {
+        let request = criteria_to_search_request(&criteria(json!({"search": "   "})));
+
+        assert_eq!(request["search"], "*");
+        assert_eq!(request["orderby"], "imdb_score desc");
+    }
+
+    #[test]
+    fn single_quotes_are_doubled() {
+        assert_eq!(filter(json!({"mpaa": "PG'13"})), "mpaa eq 'PG''13'");
+        assert_eq!(
+            filter(json!({"keywords": ["Director's Cut"]})),
+            "keywords/any(k: k eq 'director''s cut')"
+        );
+        assert_eq!(
+            filter(json!({"mpaa_exclude": ["R", "NC-17'); drop"]})),
+            "not search.in(mpaa, 'R|NC-17''); drop', '|')"
+        );
+    }
+
+    #[test]
+    fn genres_are_title_cased_for_search_in() {
+        assert_eq!(
+            filter(json!({"genre": "science fiction, DRAMA,"})),
+            "genres/any(g: search.in(g, 'Science Fiction|Drama', '|'))"
+        );
+        assert_eq!(
+            filter(json!({"genres_any": ["tv movie", "war"]})),
+            "genres/any(g: search.in(g, 'TV Movie|War', '|'))"
+        );
+    }
+
+    #[test]
+    fn every_required_genre_gets_its_own_clause() {
+        assert_eq!(
+            filter(json!({
+                "genres_all": ["action", "comedy"],
+                "genres_exclude": ["horror", "romance"]
+            })),
+            "genres/any(g: g eq 'Action') and genres/any(g: g eq 'Comedy') and \
+             not genres/any(g: search.in(g, 'Horror|Romance', '|'))"
+        );
+    }
+
+    #[test]
+    fn mpaa_lists_use_search_in() {
+        assert_eq!(
+            filter(json!({"mpaa_any": ["PG", "PG-13"]})),
+            "search.in(mpaa, 'PG|PG-13', '|')"
+        );
+    }
+
+    #[test]
+    fn loose_dates_become_range_bounds() {
+        assert_eq!(
+            filter(json!({"release_date_min": "1994", "release_date_max": "1990s"})),
+            "release_date ge '1994-01-01' and release_date le '1999-12-31'"
+        );
+        assert_eq!(
+            filter(json!({"release_date_min": "1990s", "release_date_max": "1996-02"})),
+            "release_date ge '1990-01-01' and release_date le '1996-02-29'"
+        );
+        assert_eq!(
+            filter(json!({"release_date_max": " 2001-09-11 "})),
+            "release_date le '2001-09-11'"
+        );
+    }
+
+    #[test]
+    fn unparseable_dates_are_still_quoted() {
+        assert_eq!(
+            filter(json!({"release_date_min": "last summer's"})),
+            "release_date ge 'last summer''s'"
+        );
+    }
+
+    #[test]
+    fn cast_names_become_fuzzy_matches() {
+        assert_eq!(
+            filter(json!({"cast": ["Tom Hanks", "Penélope O'Connor-Cruz", "  "]})),
+            "search.ismatch('tom hanks~1', 'cast/name', 'full', 'all') and \
+             search.ismatch('penelope~2 oconnor~1 cruz~1', 'cast/name', 'full', 'all')"
+        );
+    }
+
+    #[test]
+    fn money_ranges_leave_out_unknown_values() {
+        assert_eq!(
+            filter(json!({"budget_min": 1000000, "revenue_max": 5000000})),
+            "budget gt 0 and budget ge 1000000 and revenue gt 0 and revenue le 5000000"
+        );
+    }
+
+    #[test]
+    fn keyword_exclusions_are_lowercased() {
+        assert_eq!(
+            filter(json!({"keywords_exclude": ["Zombie", "Found Footage"]})),
+            "not keywords/any(k: search.in(k, 'zombie|found footage', '|'))"
+        );
+    }
+
+    #[test]
+    fn numeric_ranges() {
+        assert_eq!(
+            filter(json!({
+                "score_min": 6,
+                "score_max": 9.5,
+                "vote_count_min": 500,
+                "popularity_min": 10.5,
+                "popularity_max": 80
+            })),
+            "imdb_score ge 6 and imdb_score le 9.5 and vote_count ge 500 and \
+             popularity ge 10.5 and popularity le 80"
+        );
+    }
+}
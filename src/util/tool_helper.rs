@@ -1,66 +1,144 @@
 use log::debug;
+use utoipa::PartialSchema;
 
 use crate::model::chat_completion_request::{RequestTool, ToolFunction};
+use crate::model::movies::movie_criteria::MovieCriteria;
+use crate::util::criteria_helper::{GENRES, MPAA_RATINGS};
+
+// Calling this tool ends the chat turn with a list of movies
+pub const FILTER_TOOL_NAME: &str = "filter_movies";
 
 pub fn return_filter_tool() -> RequestTool {
     let filter_function = ToolFunction::builder()
-    .name("filter_movies".to_string())
-    .description("Filters movies based on the movie criteria. Requires both movie_criteria and top_rated_movies to filter down.".to_string())
-    .parameters(serde_json::json!({
-        "type": "object",
-        "properties": {
-            "movie_criteria": {
-                "type": "object",
-                "properties": {
-                    "search": { "type": "string" },
-                    "genre": { "type": "string" },
-                    "mpaa": { "type": "string" },
-                    "release_date_min": { "type": "string" },
-                    "release_date_max": { "type": "string" },
-                    "score_min": { "type": "number" },
-                    "score_max": { "type": "number" },
-                    "natural_language": { "type": "string" }
-                },
-                "required": ["search"]
+        .name(FILTER_TOOL_NAME.to_string())
+        .description("Filters movies based on the movie criteria.".to_string())
+        .parameters(serde_json::json!({
+            "type": "object",
+            "properties": {
+                "movie_criteria": criteria_schema()
             },
-            "top_rated_movies": {
-                "type": "array",
-                "items": {
-                    "type": "object",
-                    "properties": {
-                        "backdrop_path": { "type": "string" },
-                        "id": { "type": "integer" },
-                        "title": { "type": "string" },
-                        "poster_path": { "type": "string" },
-                        "release_date": { "type": "string" },
-                        "vote_average": { "type": "number" },
-                        "vote_count": { "type": "integer" },
-                        "popularity": { "type": "number" },
-                        "overview": { "type": "string" },
-                        "imdb_id": { "type": "string" },
-                        "budget": { "type": "integer" },
-                        "homepage": { "type": "string" },
-                        "revenue": { "type": "integer" },
-                        "runtime": { "type": "integer" },
-                        "tagline": { "type": "string" },
-                        "genres": { "type": "array", "items": { "type": "string" } },
-                        "cast": { "type": "array", "items": { "type": "object" } },
-                        "keywords": { "type": "array", "items": { "type": "string" } },
-                        "mpaa": { "type": "string" },
-                        "summaries": { "type": "array", "items": { "type": "string" } },
-                        "synopsis": { "type": "string" },
-                        "imdb_score": { "type": "number" }
-                    },
-                    "required": ["backdrop_path", "id", "title", "poster_path", "release_date", "vote_average", "vote_count", "popularity", "mpaa", "imdb_score"]
-                }
-            }
-        },
-        "required": ["movie_criteria", "top_rated_movies"]
-    }))
-    .build();
+            "required": ["movie_criteria"]
+        }))
+        .build();
 
     let filter_tool = RequestTool::builder().function(filter_function).build();
     debug!("filter_tool: {:?}", filter_tool);
 
     filter_tool
 }
+
+/**
+ * JSON schema of `MovieCriteria`, generated from the type so it always matches what deserializes.
+ *
+ * The genre and MPAA descriptions are extended with the values validation accepts.
+ */
+pub fn criteria_schema() -> serde_json::Value {
+    let mut schema = serde_json::to_value(MovieCriteria::schema()).unwrap_or_default();
+
+    for field in ["genre", "genres_any", "genres_all", "genres_exclude"] {
+        append_allowed_values(&mut schema, field, GENRES);
+    }
+    for field in ["mpaa", "mpaa_any", "mpaa_exclude"] {
+        append_allowed_values(&mut schema, field, MPAA_RATINGS);
+    }
+
+    schema
+}
+
+/**
+ * `criteria_schema` in the form Structured Outputs accepts with `strict` set.
+ *
+ * Strict mode needs every property listed as required (nullable ones stay optional through their `null` type),
+ * no additional properties, and none of the unsupported `format`/`minimum`/`maximum` keywords. Ranges are still
+ * enforced by `validate_criteria`.
+ */
+pub fn strict_criteria_schema() -> serde_json::Value {
+    let mut schema = criteria_schema();
+
+    if let Some(properties) = schema["properties"].as_object_mut() {
+        for property in properties.values_mut() {
+            if let Some(property) = property.as_object_mut() {
+                property.remove("format");
+                property.remove("minimum");
+                property.remove("maximum");
+            }
+        }
+    }
+
+    schema["required"] = serde_json::json!(criteria_fields());
+    schema["additionalProperties"] = serde_json::json!(false);
+
+    schema
+}
+
+fn append_allowed_values(schema: &mut serde_json::Value, field: &str, values: &[&str]) {
+    let pointer = format!("/properties/{}/description", field);
+    if let Some(description) = schema.pointer_mut(&pointer) {
+        *description = serde_json::Value::String(format!(
+            "{}. Each value should be one of: {}.",
+            description
+                .as_str()
+                .unwrap_or_default()
+                .trim_end_matches('.'),
+            values.join(", ")
+        ));
+    }
+}
+
+// Field names accepted by `MovieCriteria`
+pub fn criteria_fields() -> Vec<String> {
+    criteria_schema()["properties"]
+        .as_object()
+        .map(|properties| properties.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+/**
+ * Describes the `MovieCriteria` JSON shape for a prompt, one commented line per field.
+ *
+ * e.g. `"score_min"?: number, // The minimum vote/score/rating to filter on. 0-10 scale.`
+ */
+pub fn describe_criteria_fields() -> String {
+    let schema = criteria_schema();
+    let properties = match schema["properties"].as_object() {
+        Some(properties) => properties,
+        None => return String::from("{}"),
+    };
+
+    let last = properties.len().saturating_sub(1);
+    let lines: Vec<String> = properties
+        .iter()
+        .enumerate()
+        .map(|(index, (name, property))| {
+            format!(
+                "  \"{}\"?: {}{} // {}",
+                name,
+                json_type(property),
+                if index < last { "," } else { "" },
+                property["description"].as_str().unwrap_or_default()
+            )
+        })
+        .collect();
+
+    format!("{{\n{}\n}}", lines.join("\n"))
+}
+
+// The non-null JSON type of a property, e.g. "string" for ["string", "null"] and "string[]" for an array
+fn json_type(property: &serde_json::Value) -> String {
+    let type_ = match &property["type"] {
+        serde_json::Value::String(type_) => type_.clone(),
+        serde_json::Value::Array(types) => types
+            .iter()
+            .filter_map(|x| x.as_str())
+            .filter(|x| *x != "null")
+            .collect::<Vec<&str>>()
+            .join(" | "),
+        _ => String::from("any"),
+    };
+
+    if type_ == "array" {
+        format!("{}[]", json_type(&property["items"]))
+    } else {
+        type_
+    }
+}
@@ -47,4 +47,38 @@ impl VectorMathHelper {
 
         dot_product / (magnitude_a * magnitude_b)
     }
+
+    /**
+     * Calculates the element-wise mean of the vectors.
+     *
+     * @param vectors - The vectors to average.
+     * @returns The centroid of the vectors.
+     * @throws Will throw an error if there are no vectors or if they are not all the same length.
+     */
+    pub fn centroid(vectors: &[&[f32]]) -> Vec<f32> {
+        if vectors.is_empty() {
+            panic!("Cannot calculate the centroid of no vectors");
+        }
+
+        let mut centroid = vec![0.0; vectors[0].len()];
+        for vector in vectors {
+            Self::validate_vectors(&centroid, vector);
+            for (sum, value) in centroid.iter_mut().zip(vector.iter()) {
+                *sum += value;
+            }
+        }
+
+        let count = vectors.len() as f32;
+        centroid.iter().map(|sum| sum / count).collect()
+    }
+
+    /**
+     * Calculates the length of a vector.
+     *
+     * @param vector - The vector to measure.
+     * @returns The Euclidean norm of the vector.
+     */
+    pub fn magnitude(vector: &[f32]) -> f32 {
+        vector.iter().map(|x| x * x).sum::<f32>().sqrt()
+    }
 }